@@ -0,0 +1,68 @@
+//! Compare the dense and the packed backend on large grids, and time a larger than life rule.
+//!
+//! run with `cargo bench --bench step`
+
+use std::time::{Duration, Instant};
+
+use game_of_life::{packed::PackedGrid, random, rules::Rule, topology::Topology, universe::{Backend, Universe}};
+
+const GENERATIONS: u32 = 10;
+
+/** a deterministic soup with roughly a third of the cells alive */
+fn soup(width: u32, height: u32) -> Vec<[u32; 2]> {
+    random::soup(1, width, height, 1.0 / 3.0)
+}
+
+fn time_universe(universe: &mut Universe) -> Duration {
+    let start = Instant::now();
+    for _ in 0..GENERATIONS {
+        universe.next_epoch();
+    }
+    start.elapsed() / GENERATIONS
+}
+
+fn time_packed(mut grid: PackedGrid) -> Duration {
+    let rule = Rule::conway();
+    let start = Instant::now();
+    for _ in 0..GENERATIONS {
+        grid = grid.step(&rule, Topology::Torus);
+    }
+    start.elapsed() / GENERATIONS
+}
+
+fn main() {
+    for size in [1024, 2048, 4096] {
+        let living = soup(size, size);
+        let mut dense = Universe::new(size, size);
+        dense.init_cells(living.clone());
+        let mut packed = dense.clone();
+        packed.set_backend(Backend::Packed);
+        let mut grid = PackedGrid::new(size, size);
+        for [row, col] in living {
+            grid.set(row, col, true);
+        }
+
+        let dense_time = time_universe(&mut dense);
+        let packed_time = time_universe(&mut packed);
+        assert_eq!(dense.cells_to_arr(), packed.cells_to_arr());
+        let raw_time = time_packed(grid);
+
+        println!(
+            "{0}x{0}: dense {1:?}/gen, packed {2:?}/gen ({3:.1}x), packed without unpacking {4:?}/gen ({5:.1}x)",
+            size,
+            dense_time,
+            packed_time,
+            dense_time.as_secs_f64() / packed_time.as_secs_f64(),
+            raw_time,
+            dense_time.as_secs_f64() / raw_time.as_secs_f64(),
+        );
+    }
+
+    // bosco's rule counts 121 cells per cell, the summed-area table makes that 4 lookups
+    for size in [1024, 2048] {
+        let mut universe = Universe::new(size, size);
+        universe.set_rule("R5,C0,M1,S34..58,B34..45,NM").unwrap();
+        universe.init_cells(soup(size, size));
+        println!("{0}x{0}: R5,C0,M1,S34..58,B34..45,NM {1:?}/gen", size, time_universe(&mut universe));
+    }
+}
@@ -0,0 +1,353 @@
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    analysis::{period::PeriodKind, spaceship::normalise},
+    formats::{ParseError, Pattern},
+    rules::Rule,
+    universe::Universe,
+};
+
+/** how long an object is run on its own to find its period */
+const CLASSIFY_GENERATIONS: u32 = 128;
+
+/** the empty border around an object simulated on its own */
+const PADDING: u32 = 16;
+
+/** the code of an object which did not repeat within `CLASSIFY_GENERATIONS` generations */
+pub const UNKNOWN: &str = "zz_UNKNOWN";
+
+/** the code of the empty pattern */
+pub const EMPTY: &str = "xs0_0";
+
+/** the characters used for the 5-bit columns of a strip, and for the length of a run of empty columns */
+const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+const STRIP_HEIGHT: u32 = 5;
+
+/**
+ * encode a pattern in extended wechsler format, the part of an apgcode after the underscore.
+ * the pattern is cut into strips of 5 rows, each column of a strip is a 5-bit digit with the
+ * top row as its least significant bit, and strips are separated by `z`.
+ * trailing empty columns of a strip are dropped and runs of empty columns are shortened
+ * to `w` (2), `x` (3) or `y` followed by a digit (4 to 39)
+ */
+pub fn wechsler(cells: &[[u32; 2]]) -> String {
+    let width = cells.iter().map(|[_, col]| col + 1).max().unwrap_or(0);
+    let height = cells.iter().map(|[row, _]| row + 1).max().unwrap_or(0);
+    let strip_count = height.div_ceil(STRIP_HEIGHT);
+
+    let mut columns = vec![vec![0u8; width as usize]; strip_count as usize];
+    for [row, col] in cells {
+        columns[(row / STRIP_HEIGHT) as usize][*col as usize] |= 1 << (row % STRIP_HEIGHT);
+    }
+
+    let strips: Vec<String> = columns.iter().map(|strip| encode_strip(strip)).collect();
+    strips.join("z")
+}
+
+fn encode_strip(columns: &[u8]) -> String {
+    let used = columns.iter().rposition(|column| *column != 0).map_or(0, |last| last + 1);
+    let mut encoded = String::new();
+    let mut empty = 0;
+    for column in &columns[..used] {
+        if *column == 0 {
+            empty += 1;
+            continue;
+        }
+        push_empty_run(&mut encoded, empty);
+        empty = 0;
+        encoded.push(DIGITS[*column as usize] as char);
+    }
+    encoded
+}
+
+fn push_empty_run(encoded: &mut String, mut empty: usize) {
+    while empty > 0 {
+        let run = empty.min(39);
+        match run {
+            1 => encoded.push('0'),
+            2 => encoded.push('w'),
+            3 => encoded.push('x'),
+            _ => {
+                encoded.push('y');
+                encoded.push(DIGITS[run - 4] as char);
+            }
+        }
+        empty -= run;
+    }
+}
+
+/** the pattern moved so that its bounding box starts at `[0, 0]`, with its cells sorted */
+fn normalised(cells: &[[u32; 2]]) -> Vec<[u32; 2]> {
+    let top = cells.iter().map(|[row, _]| *row).min().unwrap_or(0);
+    let left = cells.iter().map(|[_, col]| *col).min().unwrap_or(0);
+    let mut normalised: Vec<[u32; 2]> = cells.iter().map(|[row, col]| [row - top, col - left]).collect();
+    normalised.sort_unstable();
+    normalised
+}
+
+/** the pattern under each of the 8 rotations and reflections of the square */
+pub fn orientations(cells: &[[u32; 2]]) -> Vec<Vec<[u32; 2]>> {
+    let cells = normalised(cells);
+    let last_col = cells.iter().map(|[_, col]| *col).max().unwrap_or(0);
+    let last_row = cells.iter().map(|[row, _]| *row).max().unwrap_or(0);
+
+    let mut orientations = vec![];
+    for transpose in [false, true] {
+        for flip_rows in [false, true] {
+            for flip_cols in [false, true] {
+                let oriented: Vec<[u32; 2]> = cells.iter()
+                    .map(|&[row, col]| {
+                        let row = if flip_rows { last_row - row } else { row };
+                        let col = if flip_cols { last_col - col } else { col };
+                        if transpose { [col, row] } else { [row, col] }
+                    })
+                    .collect();
+                orientations.push(normalised(&oriented));
+            }
+        }
+    }
+    orientations
+}
+
+/**
+ * the canonical wechsler encoding of an object going through `phases`:
+ * the shortest encoding of any phase in any orientation, ties broken lexicographically
+ */
+pub fn canonical(phases: &[Vec<[u32; 2]>]) -> String {
+    phases.iter()
+        .flat_map(|phase| orientations(phase))
+        .map(|oriented| wechsler(&oriented))
+        .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+        .unwrap_or_default()
+}
+
+/**
+ * the canonical apgcode of a single object, e.g. `xs4_33` (block), `xp2_7` (blinker) or
+ * `xq4_153` (glider): the kind of object and its population or period, followed by the
+ * shortest and then lexicographically smallest encoding of any of its phases and orientations
+ */
+pub fn encode(cells: &[[u32; 2]], rule: &Rule) -> String {
+    if cells.is_empty() {
+        return EMPTY.to_string();
+    }
+    let shape = normalised(cells);
+    let Some(isolated) = isolate(&shape, rule, PADDING) else {
+        return UNKNOWN.to_string();
+    };
+
+    if let Some(spaceship) = isolated.detect_spaceship(CLASSIFY_GENERATIONS) {
+        if spaceship.generation == 0 {
+            return format!("xq{}_{}", spaceship.period, canonical(&phases(&isolated, spaceship.period)));
+        }
+        return UNKNOWN.to_string();
+    }
+
+    let periodicity = isolated.detect_period(CLASSIFY_GENERATIONS);
+    match periodicity.kind {
+        _ if periodicity.generation != 0 => UNKNOWN.to_string(),
+        PeriodKind::StillLife => format!("xs{}_{}", shape.len(), canonical(&[shape])),
+        PeriodKind::Oscillator => {
+            format!("xp{}_{}", periodicity.period, canonical(&phases(&isolated, periodicity.period)))
+        }
+        PeriodKind::DiesOut | PeriodKind::Undetermined => UNKNOWN.to_string(),
+    }
+}
+
+/** the living `[row, col]` cells of an `xs`, `xp` or `xq` apgcode, in row-major order */
+pub fn decode(apgcode: &str) -> Result<Vec<[u32; 2]>, ParseError> {
+    let invalid = || ParseError::InvalidApgcodePrefix(apgcode.to_string());
+    let (prefix, encoded) = apgcode.split_once('_').ok_or_else(invalid)?;
+    let number = prefix.strip_prefix("xs")
+        .or_else(|| prefix.strip_prefix("xp"))
+        .or_else(|| prefix.strip_prefix("xq"))
+        .ok_or_else(invalid)?;
+    number.parse::<u32>().map_err(|_| invalid())?;
+
+    let mut cells = vec![];
+    let offset = prefix.len() + 1;
+    let mut characters = encoded.char_indices().map(|(index, character)| (offset + index, character));
+    let (mut strip, mut col) = (0, 0);
+    // one character at a time, as the run after a `y` may be a `z` which doesn't end the strip then
+    while let Some((position, character)) = characters.next() {
+        let invalid = |position| ParseError::InvalidWechsler { position };
+        match character {
+            'w' => col += 2,
+            'x' => col += 3,
+            'y' => {
+                let run = characters.next();
+                let length = run.and_then(|(_, run)| digit(run));
+                col += 4 + length.ok_or_else(|| invalid(run.map_or(position + 1, |(position, _)| position)))?;
+            }
+            'z' => {
+                strip += 1;
+                col = 0;
+            }
+            _ => {
+                let column = digit(character).filter(|value| *value < 32).ok_or_else(|| invalid(position))?;
+                for bit in 0..STRIP_HEIGHT {
+                    if column >> bit & 1 == 1 {
+                        cells.push([strip * STRIP_HEIGHT + bit, col]);
+                    }
+                }
+                col += 1;
+            }
+        }
+    }
+    cells.sort_unstable();
+    Ok(cells)
+}
+
+fn digit(character: char) -> Option<u32> {
+    DIGITS.iter().position(|digit| *digit as char == character).map(|value| value as u32)
+}
+
+/**
+ * a torus holding nothing but `cells` (relative to their bounding box) surrounded by `padding` dead cells,
+ * `None` if that is too large for a universe
+ */
+pub(crate) fn isolate(cells: &[[u32; 2]], rule: &Rule, padding: u32) -> Option<Universe> {
+    let padded = |extent: u32| extent.checked_add(padding.checked_mul(2)?);
+    let width = padded(cells.iter().map(|[_, col]| col + 1).max().unwrap_or(0))?;
+    let height = padded(cells.iter().map(|[row, _]| row + 1).max().unwrap_or(0))?;
+    let mut universe = Universe::try_new(width, height).ok()?;
+    universe.set_history_budget(0);
+    universe.replace_rule(rule.clone());
+    universe.init_cells(cells.iter().map(|[row, col]| [row + padding, col + padding]).collect());
+    Some(universe)
+}
+
+/** the shape of every generation in one period */
+fn phases(universe: &Universe, period: u32) -> Vec<Vec<[u32; 2]>> {
+    let mut universe = universe.detached();
+    (0..period)
+        .map(|_| {
+            let (shape, _) = normalise(&universe);
+            universe.next_epoch();
+            shape
+        })
+        .collect()
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /** the apgcode of all living cells taken as a single object */
+    pub fn apgcode(&self) -> String {
+        encode(&self.living_cells(), self.rule())
+    }
+
+    /** bring the object described by an apgcode to life with its top left corner at `row`, `col` */
+    pub fn load_apgcode_at(&mut self, apgcode: &str, row: u32, col: u32) -> Result<(), ParseError> {
+        let (pattern, _) = Pattern::from_coordinates(
+            &decode(apgcode)?.iter().map(|[row, col]| [*row as i64, *col as i64]).collect::<Vec<_>>(),
+        )?;
+        pattern.place(self, row, col)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::apgcode::{canonical, decode, encode, orientations, wechsler, EMPTY, UNKNOWN},
+        formats::ParseError,
+        rules::Rule,
+        universe::Universe,
+    };
+
+    #[test]
+    fn test_wechsler() {
+        assert_eq!(wechsler(&[[0, 0], [0, 1], [1, 0], [1, 1]]), "33");
+        assert_eq!(wechsler(&[[0, 0], [0, 1], [0, 2]]), "111");
+        // a second strip narrower than the first
+        assert_eq!(wechsler(&[[0, 0], [0, 3], [5, 0]]), "1w1z1");
+        // an empty strip in between
+        assert_eq!(wechsler(&[[0, 0], [10, 0]]), "1zz1");
+    }
+
+    #[test]
+    fn test_empty_runs() {
+        let row = |cols: &[u32]| cols.iter().map(|col| [0, *col]).collect::<Vec<_>>();
+        assert_eq!(wechsler(&row(&[0, 3])), "1w1");
+        assert_eq!(wechsler(&row(&[0, 4])), "1x1");
+        assert_eq!(wechsler(&row(&[0, 5])), "1y01");
+        assert_eq!(wechsler(&row(&[0, 40])), "1yz1");
+        assert_eq!(wechsler(&row(&[0, 42])), "1yzw1");
+    }
+
+    #[test]
+    fn test_orientations() {
+        let l_shape = [[0, 0], [1, 0], [1, 1]];
+        let mut distinct = orientations(&l_shape);
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(distinct.len(), 4);
+        assert!(distinct.contains(&vec![[0, 0], [0, 1], [1, 1]]));
+    }
+
+    #[test]
+    fn test_canonical() {
+        let beehive = vec![[0, 1], [0, 2], [1, 0], [1, 3], [2, 1], [2, 2]];
+        assert_eq!(canonical(&[beehive]), "696");
+        let blinker = [vec![[0, 0], [0, 1], [0, 2]], vec![[0, 1], [1, 1], [2, 1]]];
+        assert_eq!(canonical(&blinker), "7");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let codes = [
+            "xs4_33", "xs6_696", "xs7_2596", "xs5_253", "xs4_252",
+            "xp2_7", "xp2_7e", "xp2_318c", "xp3_co9nas0san9oczgoldlo0oldlogz1047210127401",
+            "xq4_153", "xq4_6frc",
+        ];
+        for code in codes {
+            assert_eq!(encode(&decode(code).unwrap(), &Rule::conway()), code);
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode("xq4_153").unwrap(), vec![[0, 0], [0, 1], [0, 2], [1, 2], [2, 1]]);
+        assert_eq!(decode("xs2_1y01").unwrap(), vec![[0, 0], [0, 5]]);
+        assert!(decode("xs0_0").unwrap().is_empty());
+        assert_eq!(decode("xs4_3w3z3").unwrap(), vec![[0, 0], [0, 3], [1, 0], [1, 3], [5, 0], [6, 0]]);
+
+        assert_eq!(decode("ov_s4"), Err(ParseError::InvalidApgcodePrefix("ov_s4".to_string())));
+        assert_eq!(decode("xsq_33"), Err(ParseError::InvalidApgcodePrefix("xsq_33".to_string())));
+        assert_eq!(decode("xs4_3!"), Err(ParseError::InvalidWechsler { position: 5 }));
+        assert_eq!(decode("xs4_3y"), Err(ParseError::InvalidWechsler { position: 6 }));
+        assert_eq!(decode("xs4_3z3y!"), Err(ParseError::InvalidWechsler { position: 8 }));
+    }
+
+    #[test]
+    fn test_long_empty_runs() {
+        // a run of 39 empty columns is written as `yz`, which must not end the strip
+        for (cells, encoded) in [
+            (vec![[0, 0], [0, 40]], "1yz1"),
+            (vec![[0, 0], [0, 50]], "1yzy61"),
+            (vec![[0, 0], [0, 40], [5, 0]], "1yz1z1"),
+        ] {
+            assert_eq!(wechsler(&cells), encoded);
+            assert_eq!(decode(&format!("xs{}_{}", cells.len(), encoded)).unwrap(), cells);
+        }
+    }
+
+    #[test]
+    fn test_encode_unusual_objects() {
+        assert_eq!(encode(&[], &Rule::conway()), EMPTY);
+        // an r-pentomino is still changing long after the search gives up
+        assert_eq!(encode(&[[0, 1], [0, 2], [1, 0], [1, 1], [2, 1]], &Rule::conway()), UNKNOWN);
+        // a blinker is a still life where nothing is born on 3 neightbours
+        assert_eq!(encode(&[[0, 0], [0, 1], [0, 2]], &"B/S012345678".parse().unwrap()), "xs3_7");
+        // too large to be run on its own
+        assert_eq!(encode(&[[0, 0], [8191, 8191]], &Rule::conway()), UNKNOWN);
+    }
+
+    #[test]
+    fn test_universe() {
+        let mut universe = Universe::new(12, 12);
+        universe.load_apgcode_at("xq4_153", 4, 5).unwrap();
+        assert_eq!(universe.living_cells(), vec![[4, 5], [4, 6], [4, 7], [5, 7], [6, 6]]);
+        assert_eq!(universe.apgcode(), "xq4_153");
+        assert_eq!(universe.load_apgcode_at("xs4_33", 11, 0), Err(ParseError::DoesNotFit { width: 2, height: 2 }));
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    analysis::{
+        apgcode::{encode, isolate},
+        spaceship::{normalise_cells, relative_to},
+    },
+    topology::Topology,
+    universe::Universe,
+};
+
+/** how long two objects are run together to tell whether they interact */
+const INTERACTION_GENERATIONS: u32 = 8;
+
+const ORTHOGONAL: [(i64, i64); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+
+/** how many objects of each kind a universe holds, the most common first */
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Census {
+    entries: Vec<(String, u32)>,
+}
+
+impl Census {
+    /** `(apgcode, count)` pairs sorted by descending count, then by apgcode */
+    pub fn entries(&self) -> &[(String, u32)] {
+        &self.entries
+    }
+}
+
+#[wasm_bindgen]
+impl Census {
+    /** the number of distinct kinds of objects */
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn code(&self, index: usize) -> Option<String> {
+        self.entries.get(index).map(|(code, _)| code.clone())
+    }
+
+    pub fn count(&self, index: usize) -> Option<u32> {
+        self.entries.get(index).map(|(_, count)| *count)
+    }
+
+    /** how many objects have the given apgcode */
+    pub fn count_of(&self, code: &str) -> u32 {
+        self.entries.iter().find(|(entry, _)| entry == code).map_or(0, |(_, count)| *count)
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /**
+     * separate the living cells into objects and count them by apgcode.
+     * cells which are close enough to affect each other are only kept as one object
+     * if running them together differs from running them apart, so touching still lifes
+     * (pseudo still lifes) are counted as their parts
+     */
+    pub fn census(&self) -> Census {
+        let mut counts: HashMap<String, u32> = HashMap::new();
+        // cells further than 2 apart can't affect each other in the next generation
+        let nearby: Vec<(i64, i64)> = (-2..=2)
+            .flat_map(|row| (-2..=2).map(move |col| (row, col)))
+            .filter(|offset| *offset != (0, 0))
+            .collect();
+
+        for cluster in components(self, &self.living_cells(), &nearby) {
+            for object in separate(self, components(self, &cluster, &ORTHOGONAL)) {
+                *counts.entry(classify(self, &object)).or_default() += 1;
+            }
+        }
+
+        let mut entries: Vec<(String, u32)> = counts.into_iter().collect();
+        entries.sort_by(|(code_a, count_a), (code_b, count_b)| count_b.cmp(count_a).then_with(|| code_a.cmp(code_b)));
+        Census { entries }
+    }
+}
+
+/** split `cells` into groups connected through the given neightbour offsets */
+fn components(universe: &Universe, cells: &[[u32; 2]], offsets: &[(i64, i64)]) -> Vec<Vec<[u32; 2]>> {
+    let (width, height, topology) = (universe.width(), universe.height(), universe.topology());
+    let index: HashMap<[u32; 2], usize> = cells.iter().enumerate().map(|(idx, cell)| (*cell, idx)).collect();
+    let mut parents: Vec<usize> = (0..cells.len()).collect();
+
+    fn root(parents: &mut [usize], mut idx: usize) -> usize {
+        while parents[idx] != idx {
+            parents[idx] = parents[parents[idx]];
+            idx = parents[idx];
+        }
+        idx
+    }
+
+    for (idx, [row, col]) in cells.iter().enumerate() {
+        for (row_delta, col_delta) in offsets {
+            let resolved = topology.resolve(*row as i64 + row_delta, *col as i64 + col_delta, width, height);
+            if let Some(&other) = resolved.and_then(|(row, col)| index.get(&[row, col])) {
+                let (a, b) = (root(&mut parents, idx), root(&mut parents, other));
+                parents[a] = b;
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<[u32; 2]>> = HashMap::new();
+    for (idx, cell) in cells.iter().enumerate() {
+        groups.entry(root(&mut parents, idx)).or_default().push(*cell);
+    }
+    let mut groups: Vec<Vec<[u32; 2]>> = groups.into_values().collect();
+    groups.sort_unstable();
+    groups
+}
+
+/** merge the parts of a cluster that interact until the remaining objects evolve independently */
+fn separate(universe: &Universe, mut objects: Vec<Vec<[u32; 2]>>) -> Vec<Vec<[u32; 2]>> {
+    while objects.len() > 1 && interact(universe, &objects) {
+        let pair = (0..objects.len())
+            .flat_map(|a| (a + 1..objects.len()).map(move |b| (a, b)))
+            .find(|&(a, b)| interact(universe, &[objects[a].clone(), objects[b].clone()]));
+        let Some((a, b)) = pair else {
+            // only all of them together interact
+            return vec![objects.concat()];
+        };
+        let merged = objects.remove(b);
+        objects[a].extend(merged);
+    }
+    objects
+}
+
+/** whether running the objects together differs from running each of them on its own */
+fn interact(universe: &Universe, objects: &[Vec<[u32; 2]>]) -> bool {
+    let (width, height) = (universe.width(), universe.height());
+    let wraps = universe.topology() == Topology::Torus;
+    let (_, corner) = normalise_cells(&objects.concat(), width, height, wraps);
+    let objects: Vec<Vec<[u32; 2]>> = objects.iter().map(|object| relative_to(object, corner, width, height)).collect();
+
+    let padding = INTERACTION_GENERATIONS + 2;
+    let together = isolate(&objects.concat(), universe.rule(), padding);
+    let apart: Option<Vec<Universe>> = objects.iter().map(|object| isolate(object, universe.rule(), padding)).collect();
+    // objects too large to be run on their own are kept together
+    let (Some(mut together), Some(mut apart)) = (together, apart) else {
+        return true;
+    };
+
+    for _ in 0..INTERACTION_GENERATIONS {
+        together.next_epoch();
+        let mut cells = vec![];
+        for universe in &mut apart {
+            universe.next_epoch();
+            cells.extend(universe.living_cells());
+        }
+        cells.sort_unstable();
+        cells.dedup();
+        if cells != together.living_cells() {
+            return true;
+        }
+    }
+    false
+}
+
+/** the apgcode of an object, whose cells may wrap around the edges of a torus */
+fn classify(universe: &Universe, object: &[[u32; 2]]) -> String {
+    let wraps = universe.topology() == Topology::Torus;
+    let (shape, _) = normalise_cells(object, universe.width(), universe.height(), wraps);
+    encode(&shape, universe.rule())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{analysis::{apgcode::UNKNOWN, census::interact}, formats::rle, topology::Topology, universe::Universe};
+
+    fn census(rle: &str, width: u32, height: u32) -> Vec<(String, u32)> {
+        let mut universe = Universe::new(width, height);
+        rle::parse(rle).unwrap().place(&mut universe, 2, 2).unwrap();
+        universe.census().entries().to_vec()
+    }
+
+    fn entry(code: &str, count: u32) -> (String, u32) {
+        (code.to_string(), count)
+    }
+
+    #[test]
+    fn test_common_objects() {
+        // two blocks, a blinker, a beehive and a glider
+        let ash = "x = 19, y = 9\n2o5b3o7bo$2o14bobo$16bobo$17bo3$2o4bo$2o5bo$5b3o!";
+        assert_eq!(census(ash, 32, 16), vec![
+            entry("xs4_33", 2),
+            entry("xp2_7", 1),
+            entry("xq4_153", 1),
+            entry("xs6_696", 1),
+        ]);
+    }
+
+    #[test]
+    fn test_pseudo_still_life() {
+        // a bi-block is stable as a whole, but so are both of its blocks
+        let bi_block = "x = 5, y = 2\n2ob2o$2ob2o!";
+        assert_eq!(census(bi_block, 10, 10), vec![entry("xs4_33", 2)]);
+    }
+
+    #[test]
+    fn test_interacting_parts() {
+        // the cells of a pulsar are only an oscillator together
+        let pulsar = "x = 13, y = 13\n2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$2b3o3b3o$o4bobo4bo$\
+                      o4bobo4bo$o4bobo4bo2$2b3o3b3o!";
+        assert_eq!(census(pulsar, 18, 18), vec![entry("xp3_co9nas0san9oczgoldlo0oldlogz1047210127401", 1)]);
+    }
+
+    #[test]
+    fn test_wraps_around_torus() {
+        let mut universe = Universe::with_topology(8, 8, Topology::Torus);
+        universe.init_cells(vec![[7, 7], [7, 0], [0, 7], [0, 0]]);
+        assert_eq!(universe.census().entries(), [entry("xs4_33", 1)]);
+    }
+
+    #[test]
+    fn test_unknown() {
+        let r_pentomino = "x = 3, y = 3\nb2o$2o$bo!";
+        assert_eq!(census(r_pentomino, 12, 12), vec![entry(UNKNOWN, 1)]);
+    }
+
+    #[test]
+    fn test_accessors() {
+        let mut universe = Universe::new(10, 10);
+        universe.init_cells(vec![[1, 1], [1, 2], [2, 1], [2, 2], [6, 3], [6, 4], [6, 5]]);
+        let census = universe.census();
+        assert_eq!(census.len(), 2);
+        assert_eq!(census.code(0), Some("xp2_7".to_string()));
+        assert_eq!(census.count(1), Some(1));
+        assert_eq!(census.code(2), None);
+        assert_eq!(census.count_of("xs4_33"), 1);
+        assert_eq!(census.count_of("xs6_696"), 0);
+        assert!(Universe::new(4, 4).census().is_empty());
+    }
+
+    #[test]
+    fn test_too_large_to_isolate() {
+        // opposite corners of the largest universe, which can't be padded and run on their own
+        let universe = Universe::with_topology(8192, 8192, Topology::Bounded);
+        assert!(interact(&universe, &[vec![[0, 0]], vec![[8191, 8191]]]));
+    }
+}
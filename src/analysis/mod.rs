@@ -0,0 +1,4 @@
+pub mod apgcode;
+pub mod census;
+pub mod period;
+pub mod spaceship;
@@ -0,0 +1,135 @@
+use std::{collections::{hash_map::DefaultHasher, HashMap}, hash::{Hash, Hasher}};
+
+use wasm_bindgen::prelude::*;
+
+use crate::universe::Universe;
+
+#[wasm_bindgen]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PeriodKind {
+    /** every cell is dead */
+    DiesOut = 0,
+    /** the cells stopped changing */
+    StillLife = 1,
+    /** the cells repeat every `period` generations */
+    Oscillator = 2,
+    /** no repetition was found within the generations searched */
+    Undetermined = 3,
+}
+
+/**
+ * the outcome of `Universe::detect_period`.
+ * `generation` is the first generation of the repeating cycle (or the one in which
+ * every cell was dead), `period` is 0 unless the universe is a still life or an oscillator
+ */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Periodicity {
+    pub kind: PeriodKind,
+    pub period: u32,
+    pub generation: u32,
+}
+
+impl Periodicity {
+    fn new(kind: PeriodKind, period: u32, generation: u32) -> Periodicity {
+        Periodicity { kind, period, generation }
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /**
+     * step a copy of the universe for up to `max_generations` generations and
+     * report whether it dies out, settles into a still life or keeps oscillating.
+     * only a hash of each generation is kept, a generation with the same hash as the current one
+     * is stepped to again to make sure a hash collision is never mistaken for a repetition
+     */
+    pub fn detect_period(&self, max_generations: u32) -> Periodicity {
+        let mut universe = self.detached();
+        let mut seen: HashMap<u64, Vec<u32>> = HashMap::new();
+
+        for generation in 0..=max_generations {
+            let cells = universe.cells_to_arr();
+            if cells.iter().all(|cell| *cell == 0) {
+                return Periodicity::new(PeriodKind::DiesOut, 0, generation);
+            }
+            let candidates = seen.entry(hash_cells(&cells)).or_default();
+            if let Some(first) = candidates.iter().copied().find(|first| self.cells_after(*first) == cells) {
+                let period = generation - first;
+                let kind = if period == 1 { PeriodKind::StillLife } else { PeriodKind::Oscillator };
+                return Periodicity::new(kind, period, first);
+            }
+            candidates.push(generation);
+            universe.next_epoch();
+        }
+        Periodicity::new(PeriodKind::Undetermined, 0, max_generations)
+    }
+}
+
+impl Universe {
+    /** the cells of a copy of the universe stepped `generations` generations */
+    fn cells_after(&self, generations: u32) -> Vec<u8> {
+        let mut universe = self.detached();
+        for _ in 0..generations {
+            universe.next_epoch();
+        }
+        universe.cells_to_arr()
+    }
+}
+
+fn hash_cells(cells: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    cells.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{analysis::period::{PeriodKind, Periodicity}, formats::plaintext, topology::Topology, universe::Universe};
+
+    fn universe(cells: Vec<[u32; 2]>) -> Universe {
+        let mut universe = Universe::with_topology(12, 12, Topology::Bounded);
+        universe.init_cells(cells);
+        universe
+    }
+
+    #[test]
+    fn test_dies_out() {
+        let periodicity = universe(vec![[5, 5], [5, 6]]).detect_period(10);
+        assert_eq!(periodicity, Periodicity { kind: PeriodKind::DiesOut, period: 0, generation: 1 });
+        assert_eq!(Universe::new(4, 4).detect_period(10).generation, 0);
+    }
+
+    #[test]
+    fn test_still_life() {
+        let block = universe(vec![[5, 5], [5, 6], [6, 5], [6, 6]]);
+        assert_eq!(block.detect_period(10), Periodicity { kind: PeriodKind::StillLife, period: 1, generation: 0 });
+
+        // the three cells of a pre-block settle after one generation
+        let pre_block = universe(vec![[5, 5], [5, 6], [6, 5]]);
+        assert_eq!(pre_block.detect_period(10), Periodicity { kind: PeriodKind::StillLife, period: 1, generation: 1 });
+    }
+
+    #[test]
+    fn test_oscillator() {
+        let blinker = universe(vec![[5, 4], [5, 5], [5, 6]]);
+        assert_eq!(blinker.detect_period(10), Periodicity { kind: PeriodKind::Oscillator, period: 2, generation: 0 });
+
+        let pulsar = plaintext::parse(
+            "..OOO...OOO\n\nO....O.O....O\nO....O.O....O\nO....O.O....O\n..OOO...OOO\n\n\
+             ..OOO...OOO\nO....O.O....O\nO....O.O....O\nO....O.O....O\n\n..OOO...OOO\n",
+        ).unwrap();
+        let mut universe = Universe::with_topology(17, 17, Topology::Bounded);
+        pulsar.place(&mut universe, 2, 2).unwrap();
+        assert_eq!(universe.detect_period(10), Periodicity { kind: PeriodKind::Oscillator, period: 3, generation: 0 });
+    }
+
+    #[test]
+    fn test_undetermined() {
+        // an r-pentomino takes over a thousand generations to settle
+        let mut universe = Universe::new(64, 64);
+        universe.init_cells(vec![[30, 31], [30, 32], [31, 30], [31, 31], [32, 31]]);
+        assert_eq!(universe.detect_period(20), Periodicity { kind: PeriodKind::Undetermined, period: 0, generation: 20 });
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{topology::Topology, universe::Universe};
+
+/**
+ * an object which repeats its shape every `period` generations, displaced by
+ * `dx` columns (positive to the right) and `dy` rows (positive downwards)
+ */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Spaceship {
+    pub dx: i32,
+    pub dy: i32,
+    pub period: u32,
+    /** the first generation of the repeating cycle */
+    pub generation: u32,
+}
+
+#[wasm_bindgen]
+impl Spaceship {
+    /** the speed in the usual notation, e.g. `c/4 diagonal`, `2c/5 orthogonal` or `(2,1)c/6` */
+    pub fn speed(&self) -> String {
+        speed(self.dx, self.dy, self.period)
+    }
+}
+
+pub fn speed(dx: i32, dy: i32, period: u32) -> String {
+    let (major, minor) = (dx.unsigned_abs().max(dy.unsigned_abs()), dx.unsigned_abs().min(dy.unsigned_abs()));
+    let divisor = gcd(gcd(major, minor), period);
+    let (major, minor, period) = (major / divisor, minor / divisor, period / divisor);
+
+    let multiple = if major == 1 { String::new() } else { major.to_string() };
+    if minor == 0 {
+        format!("{}c/{} orthogonal", multiple, period)
+    } else if minor == major {
+        format!("{}c/{} diagonal", multiple, period)
+    } else {
+        format!("({},{})c/{}", major, minor, period)
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/**
+ * the first index of the smallest range covering every occupied index.
+ * on a torus the range may wrap around the edge, it then starts right after the largest gap
+ */
+fn range_start(occupied: &[bool], wraps: bool) -> usize {
+    let Some(first) = occupied.iter().position(|occupied| *occupied) else {
+        return 0;
+    };
+    if !wraps {
+        return first;
+    }
+
+    let (mut start, mut largest_gap, mut gap) = (first, 0, 0);
+    for offset in 1..=occupied.len() {
+        let idx = (first + offset) % occupied.len();
+        if !occupied[idx] {
+            gap += 1;
+            continue;
+        }
+        if gap > largest_gap {
+            start = idx;
+            largest_gap = gap;
+        }
+        gap = 0;
+    }
+    start
+}
+
+/** the living cells relative to the corner of their bounding box, and that corner */
+pub(crate) fn normalise(universe: &Universe) -> (Vec<[u32; 2]>, [u32; 2]) {
+    let wraps = universe.topology() == Topology::Torus;
+    normalise_cells(&universe.living_cells(), universe.width(), universe.height(), wraps)
+}
+
+/** `normalise` for any cells of a `width` x `height` grid */
+pub(crate) fn normalise_cells(cells: &[[u32; 2]], width: u32, height: u32, wraps: bool) -> (Vec<[u32; 2]>, [u32; 2]) {
+    let (mut rows, mut cols) = (vec![false; height as usize], vec![false; width as usize]);
+    for [row, col] in cells {
+        rows[*row as usize] = true;
+        cols[*col as usize] = true;
+    }
+    let corner = [range_start(&rows, wraps) as u32, range_start(&cols, wraps) as u32];
+
+    let mut shape = relative_to(cells, corner, width, height);
+    shape.sort_unstable();
+    (shape, corner)
+}
+
+/** move cells so that `corner` ends up at `[0, 0]`, wrapping around the edges of the grid */
+pub(crate) fn relative_to(cells: &[[u32; 2]], [top, left]: [u32; 2], width: u32, height: u32) -> Vec<[u32; 2]> {
+    cells.iter().map(|[row, col]| [(row + height - top) % height, (col + width - left) % width]).collect()
+}
+
+/** the difference `to - from` along an axis of `length` cells, taking the shorter way around a torus */
+fn displacement(from: u32, to: u32, length: u32, wraps: bool) -> i32 {
+    let delta = to as i64 - from as i64;
+    if !wraps {
+        return delta as i32;
+    }
+    let delta = delta.rem_euclid(length as i64);
+    (if delta * 2 > length as i64 { delta - length as i64 } else { delta }) as i32
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /**
+     * step a copy of the universe for up to `max_generations` generations looking for a shape
+     * which repeats at a different position. oscillators, still lifes and patterns that die out
+     * give `None`.
+     * on a torus the objects may wrap around the edges, the other topologies are treated as bounded
+     */
+    pub fn detect_spaceship(&self, max_generations: u32) -> Option<Spaceship> {
+        let mut universe = self.detached();
+        let wraps = universe.topology() == Topology::Torus;
+        let mut seen: HashMap<Vec<[u32; 2]>, (u32, [u32; 2])> = HashMap::new();
+
+        for generation in 0..=max_generations {
+            let (shape, [top, left]) = normalise(&universe);
+            if shape.is_empty() {
+                return None;
+            }
+            if let Some(&(first, [first_top, first_left])) = seen.get(&shape) {
+                let dx = displacement(first_left, left, universe.width(), wraps);
+                let dy = displacement(first_top, top, universe.height(), wraps);
+                if dx == 0 && dy == 0 {
+                    return None;
+                }
+                return Some(Spaceship { dx, dy, period: generation - first, generation: first });
+            }
+            seen.insert(shape, (generation, [top, left]));
+            universe.next_epoch();
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{analysis::spaceship::{speed, Spaceship}, formats::plaintext, topology::Topology, universe::Universe};
+
+    #[test]
+    fn test_speed() {
+        assert_eq!(speed(1, 1, 4), "c/4 diagonal");
+        assert_eq!(speed(-2, 0, 4), "c/2 orthogonal");
+        assert_eq!(speed(0, 2, 5), "2c/5 orthogonal");
+        assert_eq!(speed(4, -4, 8), "c/2 diagonal");
+        assert_eq!(speed(1, -2, 6), "(2,1)c/6");
+        assert_eq!(speed(4, 2, 12), "(2,1)c/6");
+    }
+
+    #[test]
+    fn test_glider() {
+        let mut universe = Universe::new(20, 20);
+        universe.init_cells(vec![[5, 6], [6, 7], [7, 5], [7, 6], [7, 7]]);
+        let glider = universe.detect_spaceship(10).unwrap();
+        assert_eq!(glider, Spaceship { dx: 1, dy: 1, period: 4, generation: 0 });
+        assert_eq!(glider.speed(), "c/4 diagonal");
+    }
+
+    #[test]
+    fn test_wraps_around_torus() {
+        // a glider heading up and to the left, straddling the corner of the torus
+        let mut universe = Universe::new(12, 10);
+        universe.init_cells(vec![[9, 0], [9, 1], [9, 11], [0, 11], [1, 0]]);
+        let glider = universe.detect_spaceship(10).unwrap();
+        assert_eq!((glider.dx, glider.dy, glider.period), (-1, -1, 4));
+    }
+
+    #[test]
+    fn test_lightweight_spaceship() {
+        let lwss = plaintext::parse(".O..O\nO....\nO...O\nOOOO.\n").unwrap();
+        let mut universe = Universe::with_topology(30, 12, Topology::Bounded);
+        lwss.place(&mut universe, 4, 20).unwrap();
+        let spaceship = universe.detect_spaceship(10).unwrap();
+        assert_eq!((spaceship.dx, spaceship.dy, spaceship.period), (-2, 0, 4));
+        assert_eq!(spaceship.speed(), "c/2 orthogonal");
+    }
+
+    #[test]
+    fn test_not_a_spaceship() {
+        let mut blinker = Universe::new(8, 8);
+        blinker.init_cells(vec![[3, 2], [3, 3], [3, 4]]);
+        assert_eq!(blinker.detect_spaceship(10), None);
+        assert_eq!(Universe::new(8, 8).detect_spaceship(10), None);
+    }
+}
@@ -0,0 +1,448 @@
+use std::{
+    env, fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError},
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use game_of_life::{
+    formats::{life106, plaintext, rle, Pattern},
+    topology::Topology,
+    universe::Universe,
+};
+
+const USAGE: &str = "usage: life [OPTIONS] [PATTERN]
+
+run a pattern (.rle, .cells, .lif, .life or .mc) or a random soup in the terminal
+
+options:
+  --random               start from a random soup instead of a pattern
+  --width W              width of the universe (default: the pattern plus a margin, or 60)
+  --height H             height of the universe (default: the pattern plus a margin, or 30)
+  --seed N               seed of the random soup (default: the current time)
+  --density D            density of the random soup (default: 0.3)
+  --rule RULE            run another rule than the pattern's, e.g. B36/S23
+  --topology NAME        torus, bounded, mirror, klein or cross (default: torus)
+  --delay MS             milliseconds between generations, at most 5000 (default: 100)
+  --generations N        run N generations without drawing anything, then exit
+  --output FILE          with --generations, write the result to FILE instead of stdout,
+                         in the format given by its extension
+  -h, --help             show this message
+
+keys: space pause/resume, n step while paused, + faster, - slower, q quit";
+
+/** the empty border around a loaded pattern unless the size of the universe is given */
+const MARGIN: u32 = 10;
+
+const DEFAULT_WIDTH: u32 = 60;
+const DEFAULT_HEIGHT: u32 = 30;
+const DEFAULT_DENSITY: f64 = 0.3;
+const DEFAULT_DELAY: u64 = 100;
+const MAX_DELAY: u64 = 5000;
+
+/** how often a runner waiting for a key checks whether it was asked to terminate */
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/** set on SIGINT, SIGTERM or SIGHUP, the runner then quits as if `q` was pressed so the terminal is restored */
+static TERMINATED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    pattern: Option<PathBuf>,
+    random: bool,
+    width: Option<u32>,
+    height: Option<u32>,
+    seed: Option<u64>,
+    density: f64,
+    rule: Option<String>,
+    topology: Topology,
+    delay: u64,
+    generations: Option<u32>,
+    output: Option<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            pattern: None,
+            random: false,
+            width: None,
+            height: None,
+            seed: None,
+            density: DEFAULT_DENSITY,
+            rule: None,
+            topology: Topology::Torus,
+            delay: DEFAULT_DELAY,
+            generations: None,
+            output: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Rle,
+    Plaintext,
+    Life106,
+    Macrocell,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Result<Format, String> {
+        let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+        match extension.to_ascii_lowercase().as_str() {
+            "rle" => Ok(Format::Rle),
+            "cells" => Ok(Format::Plaintext),
+            "lif" | "life" => Ok(Format::Life106),
+            "mc" => Ok(Format::Macrocell),
+            _ => Err(format!("can't tell the format of '{}' from its extension", path.display())),
+        }
+    }
+
+    fn parse(self, contents: &str) -> Result<Pattern, String> {
+        let pattern = match self {
+            Format::Rle => rle::parse(contents),
+            Format::Plaintext => plaintext::parse(contents),
+            Format::Life106 => life106::parse(contents),
+            Format::Macrocell => Pattern::from_macrocell(contents),
+        };
+        pattern.map_err(|err| err.to_string())
+    }
+
+    fn write(self, universe: &Universe) -> Result<String, String> {
+        match self {
+            Format::Rle => Ok(universe.to_rle()),
+            Format::Plaintext => Ok(universe.to_plaintext()),
+            Format::Life106 => Ok(universe.to_life106()),
+            Format::Macrocell => universe.to_macrocell().map_err(|err| err.to_string()),
+        }
+    }
+}
+
+/** `None` if only the usage was asked for */
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--random" => options.random = true,
+            "--width" => options.width = Some(number(&arg, &value(&arg)?)?),
+            "--height" => options.height = Some(number(&arg, &value(&arg)?)?),
+            "--seed" => options.seed = Some(number(&arg, &value(&arg)?)?),
+            "--density" => options.density = number(&arg, &value(&arg)?)?,
+            "--rule" => options.rule = Some(value(&arg)?),
+            "--topology" => options.topology = topology(&value(&arg)?)?,
+            "--delay" => options.delay = number::<u64>(&arg, &value(&arg)?)?.clamp(1, MAX_DELAY),
+            "--generations" => options.generations = Some(number(&arg, &value(&arg)?)?),
+            "--output" => options.output = Some(PathBuf::from(value(&arg)?)),
+            _ if arg.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ if options.pattern.is_some() => return Err("only one pattern can be loaded".to_string()),
+            _ => options.pattern = Some(PathBuf::from(arg)),
+        }
+    }
+
+    if options.random == options.pattern.is_some() {
+        return Err("give either a pattern file or --random".to_string());
+    }
+    if options.output.is_some() && options.generations.is_none() {
+        return Err("--output only works together with --generations".to_string());
+    }
+    Ok(Some(options))
+}
+
+fn number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value '{}' for {}", value, name))
+}
+
+fn topology(name: &str) -> Result<Topology, String> {
+    match name {
+        "torus" => Ok(Topology::Torus),
+        "bounded" => Ok(Topology::Bounded),
+        "mirror" => Ok(Topology::Mirror),
+        "klein" => Ok(Topology::KleinBottle),
+        "cross" => Ok(Topology::CrossSurface),
+        _ => Err(format!("unknown topology '{}'", name)),
+    }
+}
+
+/** the universe described by the options, with the pattern in the middle of it */
+fn load(options: &Options) -> Result<Universe, String> {
+    let mut universe = match &options.pattern {
+        Some(path) => {
+            let contents = fs::read_to_string(path).map_err(|err| format!("can't read '{}': {}", path.display(), err))?;
+            let pattern = Format::from_path(path)?.parse(&contents)?;
+            let width = options.width.unwrap_or(pattern.width.saturating_add(2 * MARGIN));
+            let height = options.height.unwrap_or(pattern.height.saturating_add(2 * MARGIN));
+
+            let mut universe = sized(width, height, options.topology)?;
+            if let Some(rule) = &pattern.rule {
+                universe.replace_rule(rule.clone());
+            }
+            let (row, col) = (height.saturating_sub(pattern.height) / 2, width.saturating_sub(pattern.width) / 2);
+            pattern.place(&mut universe, row, col).map_err(|err| err.to_string())?;
+            universe
+        }
+        None => {
+            let width = options.width.unwrap_or(DEFAULT_WIDTH);
+            let height = options.height.unwrap_or(DEFAULT_HEIGHT);
+            let seed = options.seed.unwrap_or_else(|| {
+                SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_nanos() as u64)
+            });
+
+            let mut universe = sized(width, height, options.topology)?;
+            universe.randomize(seed, options.density).map_err(|err| err.to_string())?;
+            universe
+        }
+    };
+
+    if let Some(rule) = &options.rule {
+        universe.set_rule(rule).map_err(|err| err.to_string())?;
+    }
+    // nothing is ever undone here
+    universe.set_history_budget(0);
+    Ok(universe)
+}
+
+/** an empty universe, unless it would have no cells or too many */
+fn sized(width: u32, height: u32, topology: Topology) -> Result<Universe, String> {
+    Universe::try_with_topology(width, height, topology).map_err(|err| err.to_string())
+}
+
+/** run without a display and write the result to `output`, or to stdout as rle */
+fn run_headless(mut universe: Universe, generations: u32, output: Option<&Path>) -> Result<(), String> {
+    for _ in 0..generations {
+        universe.next_epoch();
+    }
+
+    match output {
+        Some(path) => {
+            let contents = Format::from_path(path)?.write(&universe)?;
+            fs::write(path, contents).map_err(|err| format!("can't write '{}': {}", path.display(), err))
+        }
+        None => {
+            print!("{}", Format::Rle.write(&universe)?);
+            Ok(())
+        }
+    }
+}
+
+/** puts the terminal into unbuffered mode without echo, and restores it when dropped */
+struct RawTerminal {
+    saved: String,
+}
+
+impl RawTerminal {
+    fn enable() -> Result<RawTerminal, String> {
+        let saved = stty(&["-g"])?;
+        stty(&["-icanon", "-echo", "min", "1"])?;
+        print!("\x1b[?25l\x1b[2J");
+        Ok(RawTerminal { saved: saved.trim().to_string() })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = stty(&[&self.saved]);
+        print!("\x1b[?25h");
+        let _ = io::stdout().flush();
+    }
+}
+
+#[cfg(unix)]
+fn handle_termination() {
+    const SIGHUP: i32 = 1;
+    const SIGINT: i32 = 2;
+    const SIGTERM: i32 = 15;
+    extern "C" {
+        fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+    }
+    extern "C" fn terminate(_: i32) {
+        TERMINATED.store(true, Ordering::SeqCst);
+    }
+    // only an atomic store happens in the handler, which is safe to do while interrupted
+    unsafe {
+        for signum in [SIGHUP, SIGINT, SIGTERM] {
+            signal(signum, terminate);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn handle_termination() {}
+
+/**
+ * the next key pressed within `timeout`, or forever without one.
+ * gives up with `Disconnected` as soon as the runner is asked to terminate
+ */
+fn next_key(pressed: &Receiver<u8>, timeout: Option<Duration>) -> Result<u8, RecvTimeoutError> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        if TERMINATED.load(Ordering::SeqCst) {
+            return Err(RecvTimeoutError::Disconnected);
+        }
+        let left = deadline.map_or(POLL_INTERVAL, |deadline| deadline.saturating_duration_since(Instant::now()));
+        match pressed.recv_timeout(left.min(POLL_INTERVAL)) {
+            Err(RecvTimeoutError::Timeout) if deadline.is_none_or(|deadline| Instant::now() < deadline) => {}
+            key => return key,
+        }
+    }
+}
+
+fn stty(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|err| format!("can't run stty: {}", err))?;
+    if !output.status.success() {
+        return Err("stdin is not a terminal, use --generations to run without one".to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn draw(universe: &Universe, delay: u64, paused: bool) -> io::Result<()> {
+    let population = universe.cells_to_arr().iter().filter(|cell| **cell == 1).count();
+    let mut stdout = io::stdout().lock();
+    write!(stdout, "\x1b[H{}\n\x1b[K", universe)?;
+    write!(
+        stdout,
+        "generation {}  population {}  {}ms{}\n\x1b[Kspace pause  n step  + faster  - slower  q quit",
+        universe.generation(),
+        population,
+        delay,
+        if paused { "  paused" } else { "" },
+    )?;
+    stdout.flush()
+}
+
+fn run_interactive(mut universe: Universe, mut delay: u64) -> Result<(), String> {
+    handle_termination();
+    let _terminal = RawTerminal::enable()?;
+
+    let (keys, pressed) = mpsc::channel();
+    thread::spawn(move || {
+        for byte in io::stdin().lock().bytes() {
+            let Ok(byte) = byte else { break };
+            if keys.send(byte).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut paused = false;
+    loop {
+        draw(&universe, delay, paused).map_err(|err| err.to_string())?;
+        let timeout = if paused { None } else { Some(Duration::from_millis(delay)) };
+        match next_key(&pressed, timeout) {
+            Ok(b' ') => paused = !paused,
+            Ok(b'n') if paused => universe.next_epoch(),
+            Ok(b'+') => delay = (delay / 2).max(1),
+            Ok(b'-') => delay = delay.saturating_mul(2).min(MAX_DELAY),
+            Ok(b'q') | Err(RecvTimeoutError::Disconnected) => break,
+            Ok(_) => {}
+            Err(RecvTimeoutError::Timeout) => universe.next_epoch(),
+        }
+    }
+    println!();
+    Ok(())
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(err) => {
+            eprintln!("life: {}\n\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let result = load(&options).and_then(|universe| match options.generations {
+        Some(generations) => run_headless(universe, generations, options.output.as_deref()),
+        None => run_interactive(universe, options.delay),
+    });
+    if let Err(err) = result {
+        eprintln!("life: {}", err);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::{Path, PathBuf}};
+
+    use game_of_life::topology::Topology;
+
+    use super::{load, parse_args, run_headless, Format, Options, MAX_DELAY};
+
+    fn args(line: &str) -> Result<Option<Options>, String> {
+        parse_args(line.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = args("--random --width 20 --height 10 --seed 7 --topology klein --generations 5").unwrap().unwrap();
+        assert_eq!(options, Options {
+            random: true,
+            width: Some(20),
+            height: Some(10),
+            seed: Some(7),
+            topology: Topology::KleinBottle,
+            generations: Some(5),
+            ..Options::default()
+        });
+        assert_eq!(args("glider.rle").unwrap().unwrap().pattern, Some(PathBuf::from("glider.rle")));
+        assert_eq!(args("--help"), Ok(None));
+
+        assert!(args("").is_err());
+        assert!(args("a.rle --random").is_err());
+        assert!(args("--random --width").is_err());
+        assert!(args("--random --width wide").is_err());
+        assert!(args("--random --output out.rle").is_err());
+        assert!(args("--random --topology sphere").is_err());
+        assert!(args("--random --verbose").is_err());
+
+        assert_eq!(args("--random --delay 18446744073709551615").unwrap().unwrap().delay, MAX_DELAY);
+        assert_eq!(args("--random --delay 0").unwrap().unwrap().delay, 1);
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(Format::from_path(Path::new("a/b.RLE")), Ok(Format::Rle));
+        assert_eq!(Format::from_path(Path::new("b.cells")), Ok(Format::Plaintext));
+        assert_eq!(Format::from_path(Path::new("b.lif")), Ok(Format::Life106));
+        assert_eq!(Format::from_path(Path::new("b.mc")), Ok(Format::Macrocell));
+        assert!(Format::from_path(Path::new("b.txt")).is_err());
+    }
+
+    #[test]
+    fn test_headless() {
+        let dir = env::temp_dir().join(format!("life-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (input, output) = (dir.join("glider.cells"), dir.join("result.rle"));
+        fs::write(&input, ".O.\n..O\nOOO\n").unwrap();
+
+        let options = args(&format!("{} --rule B3/S23 --generations 4", input.display())).unwrap().unwrap();
+        let universe = load(&options).unwrap();
+        assert_eq!((universe.width(), universe.height()), (23, 23));
+        run_headless(universe, 4, Some(&output)).unwrap();
+
+        // a glider moves one cell down and right every 4 generations
+        let result = fs::read_to_string(&output).unwrap();
+        let placed = Format::Rle.parse(&result).unwrap();
+        assert_eq!(placed.cells, vec![[11, 12], [12, 13], [13, 11], [13, 12], [13, 13]]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
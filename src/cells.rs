@@ -1,34 +1,113 @@
-
 use std::fmt;
 
-use wasm_bindgen::prelude::*;
+use wasm_bindgen::{
+    convert::{FromWasmAbi, IntoWasmAbi},
+    describe::WasmDescribe,
+    prelude::*,
+};
 
-#[wasm_bindgen]
-#[repr(u8)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum Cell {
-    Dead = 0,
-    Alive = 1,
+/**
+ * the state of a cell, stored in a single byte so js can view the cells as a `Uint8Array`.
+ * 0 is dead and 1 is alive, rules with more states (see `Rule::states`) use 2 and up
+ * for cells which are dying and no longer count as living neightbours
+ */
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cell(u8);
+
+// named like the variants of the two state enum `Cell` used to be, so they can still be matched on
+#[allow(non_upper_case_globals)]
+impl Cell {
+    pub const Dead: Cell = Cell(0);
+    pub const Alive: Cell = Cell(1);
+
+    /** any state, whether it is one of the states of a rule is checked where the cell is put into a universe */
+    pub const fn new(state: u8) -> Cell {
+        Cell(state)
+    }
+
+    pub const fn state(self) -> u8 {
+        self.0
+    }
+
+    pub fn is_alive(self) -> bool {
+        self == Cell::Alive
+    }
+}
+
+impl From<Cell> for u8 {
+    fn from(cell: Cell) -> u8 {
+        cell.0
+    }
 }
 
 impl fmt::Display for Cell {
     /** display the cell in a human readable format */
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let readable_display = match self {
+        let readable_display = match *self {
             Cell::Alive => '◼',
-            Cell::Dead => '◻'
+            Cell::Dead => '◻',
+            _ => '▣',
         };
         write!(f, "{}", readable_display)
     }
 }
 
+/** `Cell.Dead` and `Cell.Alive` for js, which passes cells as their state number */
+#[wasm_bindgen(js_name = Cell)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CellState {
+    Dead = 0,
+    Alive = 1,
+}
+
+impl From<CellState> for Cell {
+    fn from(state: CellState) -> Cell {
+        Cell(state as u8)
+    }
+}
+
+// cross the wasm boundary as the plain state number
+impl WasmDescribe for Cell {
+    fn describe() {
+        u8::describe()
+    }
+}
+
+impl IntoWasmAbi for Cell {
+    type Abi = <u8 as IntoWasmAbi>::Abi;
+
+    fn into_abi(self) -> Self::Abi {
+        self.0.into_abi()
+    }
+}
+
+impl FromWasmAbi for Cell {
+    type Abi = <u8 as FromWasmAbi>::Abi;
+
+    unsafe fn from_abi(js: Self::Abi) -> Self {
+        Cell(u8::from_abi(js))
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::cells::Cell;
+    use crate::cells::{Cell, CellState};
 
     #[test]
     fn test_display() {
         assert_eq!(Cell::Alive.to_string(), '◼'.to_string());
         assert_eq!(Cell::Dead.to_string(), '◻'.to_string());
+        assert_eq!(Cell::new(2).to_string(), '▣'.to_string());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_state() {
+        assert_eq!(Cell::new(1), Cell::Alive);
+        assert_eq!(u8::from(Cell::new(3)), 3);
+        assert!(Cell::Alive.is_alive() && !Cell::new(2).is_alive());
+        assert_eq!(Cell::from(CellState::Dead), Cell::Dead);
+        assert_eq!(Cell::from(CellState::Alive), Cell::Alive);
+    }
+}
@@ -0,0 +1,129 @@
+use wasm_bindgen::prelude::*;
+
+use crate::{formats::{ParseError, Pattern}, universe::Universe};
+
+pub const HEADER: &str = "#Life 1.06";
+
+/**
+ * parse the living cells of a pattern in the Life 1.06 format as signed `[row, col]` coordinates
+ *
+ * ```text
+ * #Life 1.06
+ * 0 -1
+ * 1 0
+ * -1 1
+ * 0 1
+ * 1 1
+ * ```
+ * every line holds the `x y` (i.e. column and row) coordinate of one living cell
+ */
+pub fn parse_coordinates(life: &str) -> Result<Vec<[i64; 2]>, ParseError> {
+    let mut lines = life.lines().enumerate().map(|(index, line)| (index + 1, line.trim()));
+    match lines.next() {
+        Some((_, header)) if header == HEADER => {}
+        _ => return Err(ParseError::MissingHeader),
+    }
+
+    let mut coordinates = vec![];
+    for (line_number, line) in lines {
+        if line.is_empty() || line.starts_with('#') { continue }
+
+        let invalid = ParseError::InvalidCoordinate { line: line_number };
+        let mut tokens = line.split_whitespace();
+        let mut coordinate = || -> Result<i64, ParseError> {
+            let token = tokens.next().ok_or(invalid.clone())?;
+            token.parse().map_err(|_| match token.chars().find(|ch| !ch.is_ascii_digit() && *ch != '-') {
+                Some(character) => ParseError::UnexpectedCharacter { line: line_number, character },
+                // only digits and signs, but not a number that fits
+                None => invalid.clone(),
+            })
+        };
+        let (col, row) = (coordinate()?, coordinate()?);
+        if tokens.next().is_some() {
+            return Err(invalid);
+        }
+        coordinates.push([row, col]);
+    }
+    Ok(coordinates)
+}
+
+/** parse a Life 1.06 pattern, translating its bounding box onto the top left corner */
+pub fn parse(life: &str) -> Result<Pattern, ParseError> {
+    let (pattern, _) = Pattern::from_coordinates(&parse_coordinates(life)?)?;
+    Ok(pattern)
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut life = format!("{}\n", HEADER);
+    for [row, col] in &pattern.cells {
+        life.push_str(&format!("{} {}\n", col, row));
+    }
+    life
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /** create a universe just big enough for the bounding box of a Life 1.06 pattern */
+    pub fn from_life106(life: &str) -> Result<Universe, ParseError> {
+        parse(life)?.to_universe()
+    }
+
+    /** bring a Life 1.06 pattern to life with the top left corner of its bounding box at `row`, `col` */
+    pub fn load_life106_at(&mut self, life: &str, row: u32, col: u32) -> Result<(), ParseError> {
+        parse(life)?.place(self, row, col)
+    }
+
+    /** the living cells as Life 1.06 coordinates, the top left cell of the universe being `0 0` */
+    pub fn to_life106(&self) -> String {
+        write(&Pattern::from_universe(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{formats::{ParseError, Pattern, life106::{parse, parse_coordinates, write}}, universe::Universe};
+
+    const GLIDER: &str = "#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n";
+
+    #[test]
+    fn test_parse_coordinates() {
+        assert_eq!(parse_coordinates(GLIDER).unwrap(), vec![[-1, 0], [0, 1], [1, -1], [1, 0], [1, 1]]);
+    }
+
+    #[test]
+    fn test_parse_translates_bounding_box() {
+        let pattern = parse(GLIDER).unwrap();
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(pattern.cells, vec![[0, 1], [1, 2], [2, 0], [2, 1], [2, 2]]);
+
+        let (_, origin) = Pattern::from_coordinates(&parse_coordinates(GLIDER).unwrap()).unwrap();
+        assert_eq!(origin, [-1, -1]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse("0 0\n"), Err(ParseError::MissingHeader));
+        assert_eq!(parse("#Life 1.06\n0 x\n"), Err(ParseError::UnexpectedCharacter { line: 2, character: 'x' }));
+        assert_eq!(parse("#Life 1.06\n0 0 0\n"), Err(ParseError::InvalidCoordinate { line: 2 }));
+        assert_eq!(parse("#Life 1.06\n3\n"), Err(ParseError::InvalidCoordinate { line: 2 }));
+        assert_eq!(parse("#Life 1.06\n1-2 0\n"), Err(ParseError::InvalidCoordinate { line: 2 }));
+        assert_eq!(parse("#Life 1.06\n0 99999999999999999999\n"), Err(ParseError::InvalidCoordinate { line: 2 }));
+        assert_eq!(parse("#Life 1.06\n0 0\n5000000000 0\n"), Err(ParseError::TooLarge));
+    }
+
+    #[test]
+    fn test_write() {
+        let pattern = parse(GLIDER).unwrap();
+        assert_eq!(write(&pattern), "#Life 1.06\n1 0\n2 1\n0 2\n1 2\n2 2\n");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut universe = Universe::new(4, 4);
+        universe.init_cells(vec![[1, 1], [1, 2], [2, 1], [2, 2]]);
+        let mut restored = Universe::new(4, 4);
+        restored.load_life106_at(&universe.to_life106(), 1, 1).unwrap();
+        assert_eq!(restored.cells_to_arr(), universe.cells_to_arr());
+        assert_eq!(Universe::from_life106(&universe.to_life106()).unwrap().cells_to_arr(), vec![1; 4]);
+    }
+}
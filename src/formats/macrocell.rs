@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    formats::{ParseError, Pattern},
+    hashlife::{HashLife, NodeId, ALIVE, DEAD, MAX_LEVEL},
+    rules::RuleError,
+    universe::{Universe, MAX_CELLS},
+};
+
+pub const HEADER: &str = "[M2]";
+
+/** the largest number of cells `Universe::from_macrocell` is willing to allocate */
+pub const MAX_DENSE_CELLS: u64 = MAX_CELLS as u64;
+
+/** leaves of a macrocell file are 8x8 squares, i.e. nodes of level 3 */
+const LEAF_LEVEL: u8 = 3;
+const LEAF_SIZE: usize = 1 << LEAF_LEVEL;
+
+/**
+ * parse a pattern in Golly's macrocell format into a quadtree
+ *
+ * ```text
+ * [M2] (golly 2.0)
+ * #R B3/S23
+ * #G 120
+ * .*$..*$***$
+ * 4 1 0 0 0
+ * ```
+ * after the header and `#` lines every line defines a node, the first line being node 1.
+ * lines made of `.`, `*` and `$` are 8x8 leaves, with `$` ending each row. other lines are
+ * `level nw ne sw se`, referring to earlier nodes by their number (0 being empty).
+ * the last node is the root, centred on the origin
+ */
+pub fn parse(macrocell: &str) -> Result<HashLife, ParseError> {
+    let mut lines = macrocell.lines().enumerate().map(|(index, line)| (index + 1, line.trim()));
+    match lines.next() {
+        Some((_, header)) if header.starts_with(HEADER) => {}
+        _ => return Err(ParseError::MissingHeader),
+    }
+
+    let mut hashlife = HashLife::new();
+    let mut nodes: Vec<NodeId> = vec![];
+    for (line_number, line) in lines {
+        if let Some(comment) = line.strip_prefix('#') {
+            let mut chars = comment.chars();
+            let kind = chars.next();
+            let value = chars.as_str().trim();
+            match kind {
+                Some('R') => hashlife.replace_rule(value.parse()?)?,
+                Some('G') => {
+                    let generation = value.parse().map_err(|_| ParseError::InvalidHeader(line.to_string()))?;
+                    hashlife.set_generation(generation);
+                }
+                _ => {}
+            }
+            continue;
+        }
+        if line.is_empty() { continue }
+
+        let node = if line.starts_with(['.', '*', '$']) {
+            parse_leaf(&mut hashlife, line, line_number)?
+        } else {
+            parse_node(&mut hashlife, &nodes, line, line_number)?
+        };
+        nodes.push(node);
+    }
+
+    let root = *nodes.last().ok_or(ParseError::MissingHeader)?;
+    hashlife.set_root(root);
+    Ok(hashlife)
+}
+
+/** write the quadtree of a universe in Golly's macrocell format */
+pub fn write(hashlife: &HashLife) -> String {
+    let mut macrocell = format!("{} (game-of-life)\n#R {}\n", HEADER, hashlife.rule());
+    if hashlife.generation() > 0 {
+        macrocell.push_str(&format!("#G {}\n", hashlife.generation()));
+    }
+
+    let (root, _) = hashlife.root();
+    let mut numbers = HashMap::new();
+    let mut lines = vec![];
+    number_node(hashlife, root, &mut numbers, &mut lines);
+    if lines.is_empty() {
+        // an empty universe still needs a root
+        lines.push("$".to_string());
+    }
+    for line in lines {
+        macrocell.push_str(&line);
+        macrocell.push('\n');
+    }
+    macrocell
+}
+
+fn parse_leaf(hashlife: &mut HashLife, line: &str, line_number: usize) -> Result<NodeId, ParseError> {
+    let mut grid = [[false; LEAF_SIZE]; LEAF_SIZE];
+    let (mut row, mut col) = (0, 0);
+    for character in line.chars() {
+        match character {
+            '.' | '*' if row < LEAF_SIZE && col < LEAF_SIZE => {
+                grid[row][col] = character == '*';
+                col += 1;
+            }
+            '$' => {
+                row += 1;
+                col = 0;
+            }
+            '.' | '*' => return Err(ParseError::InvalidNode { line: line_number }),
+            unexpected => return Err(ParseError::UnexpectedCharacter { line: line_number, character: unexpected }),
+        }
+    }
+    Ok(build_leaf(hashlife, &grid, LEAF_LEVEL, 0, 0))
+}
+
+fn build_leaf(hashlife: &mut HashLife, grid: &[[bool; LEAF_SIZE]; LEAF_SIZE], level: u8, top: usize, left: usize) -> NodeId {
+    if level == 0 {
+        return if grid[top][left] { ALIVE } else { DEAD };
+    }
+    let half = 1 << (level - 1);
+    let children = [
+        build_leaf(hashlife, grid, level - 1, top, left),
+        build_leaf(hashlife, grid, level - 1, top, left + half),
+        build_leaf(hashlife, grid, level - 1, top + half, left),
+        build_leaf(hashlife, grid, level - 1, top + half, left + half),
+    ];
+    hashlife.join(children)
+}
+
+fn parse_node(hashlife: &mut HashLife, nodes: &[NodeId], line: &str, line_number: usize) -> Result<NodeId, ParseError> {
+    let invalid = ParseError::InvalidNode { line: line_number };
+    let numbers = line.split_whitespace()
+        .map(|token| token.parse::<usize>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid.clone())?;
+    let [level, nw, ne, sw, se] = numbers[..] else {
+        return Err(invalid);
+    };
+    if level <= LEAF_LEVEL as usize || level > MAX_LEVEL as usize {
+        return Err(invalid);
+    }
+
+    let mut children = [DEAD; 4];
+    for (child, number) in children.iter_mut().zip([nw, ne, sw, se]) {
+        *child = match number {
+            0 => hashlife.empty(level as u8 - 1),
+            number => *nodes.get(number - 1).ok_or_else(|| invalid.clone())?,
+        };
+        if hashlife.node_level(*child) as usize != level - 1 {
+            return Err(invalid);
+        }
+    }
+    Ok(hashlife.join(children))
+}
+
+/** assign numbers to the non-empty nodes below `node` in the order they have to be written */
+fn number_node(hashlife: &HashLife, node: NodeId, numbers: &mut HashMap<NodeId, usize>, lines: &mut Vec<String>) -> usize {
+    if hashlife.node_population(node) == 0 {
+        return 0;
+    }
+    if let Some(number) = numbers.get(&node) {
+        return *number;
+    }
+
+    let level = hashlife.node_level(node);
+    let line = if level == LEAF_LEVEL {
+        leaf_line(hashlife, node)
+    } else {
+        let children = hashlife.children(node).map(|child| number_node(hashlife, child, numbers, lines));
+        format!("{} {} {} {} {}", level, children[0], children[1], children[2], children[3])
+    };
+    lines.push(line);
+    numbers.insert(node, lines.len());
+    lines.len()
+}
+
+fn leaf_line(hashlife: &HashLife, node: NodeId) -> String {
+    let mut grid = [[false; LEAF_SIZE]; LEAF_SIZE];
+    fill_leaf(hashlife, node, &mut grid, 0, 0);
+
+    let last_row = grid.iter().rposition(|row| row.contains(&true)).unwrap_or(0);
+    let mut line = String::new();
+    for row in &grid[..=last_row] {
+        let length = row.iter().rposition(|alive| *alive).map_or(0, |last| last + 1);
+        line.extend(row[..length].iter().map(|alive| if *alive { '*' } else { '.' }));
+        line.push('$');
+    }
+    line
+}
+
+fn fill_leaf(hashlife: &HashLife, node: NodeId, grid: &mut [[bool; LEAF_SIZE]; LEAF_SIZE], top: usize, left: usize) {
+    let level = hashlife.node_level(node);
+    if level == 0 {
+        grid[top][left] = node == ALIVE;
+        return;
+    }
+    let half = 1 << (level - 1);
+    let [nw, ne, sw, se] = hashlife.children(node);
+    fill_leaf(hashlife, nw, grid, top, left);
+    fill_leaf(hashlife, ne, grid, top, left + half);
+    fill_leaf(hashlife, sw, grid, top + half, left);
+    fill_leaf(hashlife, se, grid, top + half, left + half);
+}
+
+#[wasm_bindgen]
+impl HashLife {
+    pub fn from_macrocell(macrocell: &str) -> Result<HashLife, ParseError> {
+        parse(macrocell)
+    }
+
+    pub fn to_macrocell(&self) -> String {
+        write(self)
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /** create a universe just big enough for the bounding box of a macrocell pattern */
+    pub fn from_macrocell(macrocell: &str) -> Result<Universe, ParseError> {
+        let hashlife = parse(macrocell)?;
+        let Some([top, left, bottom, right]) = hashlife.bounding_box() else {
+            return Err(ParseError::Empty);
+        };
+
+        let (width, height) = ((right - left + 1) as u64, (bottom - top + 1) as u64);
+        if width.checked_mul(height).is_none_or(|cells| cells > MAX_DENSE_CELLS) {
+            return Err(ParseError::TooLarge);
+        }
+        hashlife.to_universe(top, left, width as u32, height as u32).map_err(|_| ParseError::TooLarge)
+    }
+
+    /** only life-like rules without B0 can be written, as those are the only ones hashlife runs */
+    pub fn to_macrocell(&self) -> Result<String, RuleError> {
+        Ok(write(&HashLife::from_universe(self)?))
+    }
+}
+
+impl Pattern {
+    /** read the living cells of a macrocell pattern, if its bounding box fits into a dense universe */
+    pub fn from_macrocell(macrocell: &str) -> Result<Pattern, ParseError> {
+        let universe = Universe::from_macrocell(macrocell)?;
+        Ok(Pattern::from_universe(&universe))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{formats::{ParseError, macrocell::{parse, write}}, hashlife::HashLife, rules::RuleError, universe::Universe};
+
+    const GLIDER: &str = "[M2] (golly 2.0)
+#R B3/S23
+#G 4
+.*$..*$***$
+4 0 0 0 1
+";
+
+    #[test]
+    fn test_parse() {
+        let hashlife = parse(GLIDER).unwrap();
+        assert_eq!(hashlife.generation(), 4);
+        assert_eq!(hashlife.population(), 5);
+        // the 16x16 root is centred on the origin, the leaf is its south east quadrant
+        assert_eq!(hashlife.bounding_box(), Some([0, 0, 2, 2]));
+        assert!(hashlife.get_cell(0, 1));
+        assert!(hashlife.get_cell(2, 0));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse(".*$\n").err(), Some(ParseError::MissingHeader));
+        assert_eq!(parse("[M2]\n.*x$\n").err(), Some(ParseError::UnexpectedCharacter { line: 2, character: 'x' }));
+        assert_eq!(parse("[M2]\n.........*$\n").err(), Some(ParseError::InvalidNode { line: 2 }));
+        assert_eq!(parse("[M2]\n.*$\n4 2 0 0 0\n").err(), Some(ParseError::InvalidNode { line: 3 }));
+        assert_eq!(parse("[M2]\n.*$\n5 1 0 0 0\n").err(), Some(ParseError::InvalidNode { line: 3 }));
+        assert_eq!(parse("[M2]\n.*$\n4 1 0 0\n").err(), Some(ParseError::InvalidNode { line: 3 }));
+        assert_eq!(parse("[M2]\n#G x\n.*$\n").err(), Some(ParseError::InvalidHeader("#G x".to_string())));
+        assert!(matches!(parse("[M2]\n#R B0/S\n.*$\n").err(), Some(ParseError::InvalidRule(RuleError::Unsupported(_)))));
+    }
+
+    #[test]
+    fn test_write() {
+        let hashlife = parse(GLIDER).unwrap();
+        // the root is shrunk down to the single leaf centred on the origin
+        assert_eq!(write(&hashlife), "[M2] (game-of-life)\n#R B3/S23\n#G 4\n$$$$.....*$......*$....***$\n");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut hashlife = HashLife::new();
+        hashlife.set_rule("B36/S23").unwrap();
+        hashlife.load_rle_at("x = 3, y = 3\nbo$2bo$3o!", -1000, 2000).unwrap();
+        hashlife.step(100).unwrap();
+
+        let restored = parse(&write(&hashlife)).unwrap();
+        assert_eq!(restored.rulestring(), "B36/S23");
+        assert_eq!(restored.generation(), 100);
+        assert_eq!(restored.bounding_box(), hashlife.bounding_box());
+        let [top, left, _, _] = hashlife.bounding_box().unwrap();
+        assert_eq!(restored.living_cells_in(top, left, 3, 3), hashlife.living_cells_in(top, left, 3, 3));
+    }
+
+    #[test]
+    fn test_dense_round_trip() {
+        let mut universe = Universe::new(20, 20);
+        universe.init_cells(vec![[0, 0], [19, 19], [7, 12]]);
+        let restored = Universe::from_macrocell(&universe.to_macrocell().unwrap()).unwrap();
+        assert_eq!(restored.cells_to_arr(), universe.cells_to_arr());
+
+        // dying states and larger neighbourhoods can't be written
+        for rulestring in ["B2/S/C3", "R2,C0,M0,S3..5,B3..4,NN", "B03/S23"] {
+            universe.set_rule(rulestring).unwrap();
+            assert!(matches!(universe.to_macrocell(), Err(RuleError::Unsupported(_))), "{}", rulestring);
+        }
+
+        // two cells far apart do not fit into a dense universe
+        let mut hashlife = HashLife::new();
+        hashlife.set_cell(0, 0, true).unwrap();
+        hashlife.set_cell(1 << 20, 1 << 20, true).unwrap();
+        assert_eq!(Universe::from_macrocell(&hashlife.to_macrocell()).err(), Some(ParseError::TooLarge));
+
+        // so many cells that counting them overflows
+        hashlife.set_cell(-1 << 61, -1 << 61, true).unwrap();
+        hashlife.set_cell(1 << 61, 1 << 61, true).unwrap();
+        assert_eq!(Universe::from_macrocell(&hashlife.to_macrocell()).err(), Some(ParseError::TooLarge));
+        assert_eq!(Universe::from_macrocell("[M2]\n$").err(), Some(ParseError::Empty));
+    }
+}
@@ -0,0 +1,198 @@
+use std::{convert::TryFrom, error::Error, fmt};
+
+use wasm_bindgen::prelude::*;
+
+use crate::{cells::Cell, rules::{Rule, RuleError}, universe::{cell_count, Universe}};
+
+pub mod life106;
+pub mod macrocell;
+pub mod plaintext;
+pub mod rle;
+
+/** a finite pattern read from (or about to be written to) a pattern file */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Pattern {
+    pub width: u32,
+    pub height: u32,
+    /** the living cells as `[row, col]` relative to the top left corner of the pattern */
+    pub cells: Vec<[u32; 2]>,
+    /** the state of each of `cells` under rules with more than two states, empty if they are all alive */
+    pub states: Vec<u8>,
+    pub rule: Option<Rule>,
+    pub name: Option<String>,
+    pub comments: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /** the pattern has no `x = .., y = ..` header line */
+    MissingHeader,
+    InvalidHeader(String),
+    InvalidRule(RuleError),
+    UnexpectedCharacter { line: usize, character: char },
+    /** a run count too large to be meaningful */
+    InvalidCount { line: usize },
+    /** a line which does not hold exactly one `x y` coordinate, or a coordinate out of range */
+    InvalidCoordinate { line: usize },
+    /** the pattern body contains more cells than its header declares */
+    ExceedsHeader { line: usize },
+    /** a quadtree node of the wrong size or referring to a node not defined before it */
+    InvalidNode { line: usize },
+    /** the bounding box of the pattern is too large to be stored in a universe */
+    TooLarge,
+    /** an apgcode which does not start with `xs`, `xp` or `xq`, a number and an underscore */
+    InvalidApgcodePrefix(String),
+    /** the extended wechsler format part of an apgcode can't be decoded at this byte of the apgcode */
+    InvalidWechsler { position: usize },
+    /** the pattern is 0 cells wide or high, which no universe can be */
+    Empty,
+    /** the pattern does not fit into the universe at the requested position */
+    DoesNotFit { width: u32, height: u32 },
+    /** a cell in a state the rule it is loaded under doesn't have */
+    InvalidState { state: u8, states: u16 },
+}
+
+impl Pattern {
+    /**
+     * build a pattern from living cells at signed `[row, col]` coordinates by translating
+     * their bounding box onto the top left corner of the pattern.
+     * also returns the coordinate which ended up at `[0, 0]`
+     */
+    pub fn from_coordinates(coordinates: &[[i64; 2]]) -> Result<(Pattern, [i64; 2]), ParseError> {
+        let Some(&[first_row, first_col]) = coordinates.first() else {
+            return Ok((Pattern::default(), [0, 0]));
+        };
+        let (mut top, mut left, mut bottom, mut right) = (first_row, first_col, first_row, first_col);
+        for &[row, col] in coordinates {
+            top = top.min(row);
+            bottom = bottom.max(row);
+            left = left.min(col);
+            right = right.max(col);
+        }
+
+        let extent = |low: i64, high: i64| {
+            high.checked_sub(low)
+                .and_then(|span| u32::try_from(span).ok())
+                .and_then(|span| span.checked_add(1))
+                .ok_or(ParseError::TooLarge)
+        };
+        let pattern = Pattern {
+            width: extent(left, right)?,
+            height: extent(top, bottom)?,
+            cells: coordinates.iter().map(|[row, col]| [(row - top) as u32, (col - left) as u32]).collect(),
+            ..Pattern::default()
+        };
+        Ok((pattern, [top, left]))
+    }
+
+    /**
+     * copy the living cells of a universe into a pattern covering the whole universe,
+     * or every cell which isn't dead if its rule has more than two states
+     */
+    pub fn from_universe(universe: &Universe) -> Pattern {
+        let width = universe.width();
+        let multistate = universe.rule().states() > 2;
+        let (cells, states): (Vec<[u32; 2]>, Vec<u8>) = universe.cells_to_arr().into_iter().enumerate()
+            .filter(|(_, state)| *state == 1 || multistate && *state != 0)
+            .map(|(index, state)| ([index as u32 / width, index as u32 % width], state))
+            .unzip();
+        Pattern {
+            width,
+            height: universe.height(),
+            cells,
+            states: if multistate { states } else { vec![] },
+            rule: Some(universe.rule().clone()),
+            ..Pattern::default()
+        }
+    }
+
+    /** the state of `cells[index]` */
+    pub fn state(&self, index: usize) -> Cell {
+        self.states.get(index).map_or(Cell::Alive, |state| Cell::new(*state))
+    }
+
+    /** make sure every cell is in one of the states of `rule` */
+    pub fn check_states(&self, rule: &Rule) -> Result<(), ParseError> {
+        match (0..self.cells.len()).map(|index| self.state(index)).find(|cell| !rule.has_state(*cell)) {
+            Some(cell) => Err(ParseError::InvalidState { state: cell.state(), states: rule.states() }),
+            None => Ok(()),
+        }
+    }
+
+    /** set the cells with their states, moved by `row`, `col` */
+    fn init(&self, universe: &mut Universe, row: u32, col: u32) -> Result<(), ParseError> {
+        self.check_states(universe.rule())?;
+        let placed = self.cells.iter().enumerate().map(|(index, [r, c])| ([row + r, col + c], self.state(index))).collect();
+        universe.init_states(placed).expect("the states were checked");
+        Ok(())
+    }
+
+    /** create a universe just big enough for the pattern, running the pattern's rule */
+    pub fn to_universe(&self) -> Result<Universe, ParseError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(ParseError::Empty);
+        }
+        if cell_count(self.width, self.height).is_none() {
+            return Err(ParseError::TooLarge);
+        }
+        let mut universe = Universe::new(self.width, self.height);
+        if let Some(rule) = self.rule.clone() {
+            universe.replace_rule(rule);
+        }
+        self.init(&mut universe, 0, 0)?;
+        Ok(universe)
+    }
+
+    /** place the pattern with its top left corner at `row`, `col` */
+    pub fn place(&self, universe: &mut Universe, row: u32, col: u32) -> Result<(), ParseError> {
+        let fits = row as u64 + self.height as u64 <= universe.height() as u64
+            && col as u64 + self.width as u64 <= universe.width() as u64;
+        if !fits {
+            return Err(ParseError::DoesNotFit { width: self.width, height: self.height });
+        }
+        self.init(universe, row, col)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::MissingHeader => write!(f, "missing `x = .., y = ..` header"),
+            ParseError::InvalidHeader(header) => write!(f, "invalid header '{}'", header),
+            ParseError::InvalidRule(err) => write!(f, "{}", err),
+            ParseError::UnexpectedCharacter { line, character } => {
+                write!(f, "unexpected character '{}' on line {}", character, line)
+            }
+            ParseError::InvalidCount { line } => write!(f, "invalid run count on line {}", line),
+            ParseError::InvalidCoordinate { line } => write!(f, "invalid coordinate on line {}", line),
+            ParseError::ExceedsHeader { line } => {
+                write!(f, "pattern exceeds the size declared in its header on line {}", line)
+            }
+            ParseError::InvalidNode { line } => write!(f, "invalid quadtree node on line {}", line),
+            ParseError::TooLarge => write!(f, "pattern is too large for a universe"),
+            ParseError::InvalidApgcodePrefix(apgcode) => write!(f, "'{}' does not start with xs, xp or xq and a number", apgcode),
+            ParseError::InvalidWechsler { position } => write!(f, "invalid extended wechsler format at byte {}", position),
+            ParseError::Empty => write!(f, "pattern is empty, a universe needs at least one cell"),
+            ParseError::DoesNotFit { width, height } => {
+                write!(f, "a {}x{} pattern does not fit into the universe", width, height)
+            }
+            ParseError::InvalidState { state, states } => {
+                write!(f, "state {} is not one of the {} states of the rule", state, states)
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+impl From<RuleError> for ParseError {
+    fn from(err: RuleError) -> Self {
+        ParseError::InvalidRule(err)
+    }
+}
+
+impl From<ParseError> for JsValue {
+    fn from(err: ParseError) -> Self {
+        js_sys::Error::new(&err.to_string()).into()
+    }
+}
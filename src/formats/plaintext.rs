@@ -0,0 +1,144 @@
+use wasm_bindgen::prelude::*;
+
+use crate::{formats::{ParseError, Pattern}, universe::Universe};
+
+/**
+ * parse a pattern in the plaintext `.cells` format
+ *
+ * ```text
+ * !Name: Glider
+ * .O.
+ * ..O
+ * OOO
+ * ```
+ */
+pub fn parse(plaintext: &str) -> Result<Pattern, ParseError> {
+    let mut pattern = Pattern::default();
+    let mut rows = vec![];
+
+    for (index, line) in plaintext.lines().enumerate() {
+        let line = line.trim_end();
+        if let Some(comment) = line.strip_prefix('!') {
+            match comment.strip_prefix("Name:") {
+                Some(name) => pattern.name = Some(name.trim().to_string()),
+                None => pattern.comments.push(comment.trim().to_string()),
+            }
+            continue;
+        }
+        rows.push((index + 1, line));
+    }
+    // blank lines are empty rows, except for the ones trailing the pattern
+    while rows.last().is_some_and(|(_, line)| line.is_empty()) {
+        rows.pop();
+    }
+
+    for (row, (line_number, line)) in rows.iter().enumerate() {
+        for (col, character) in line.chars().enumerate() {
+            match character {
+                '.' => {}
+                'O' | '*' => pattern.cells.push([row as u32, col as u32]),
+                unexpected => {
+                    return Err(ParseError::UnexpectedCharacter { line: *line_number, character: unexpected });
+                }
+            }
+        }
+        pattern.width = pattern.width.max(line.chars().count() as u32);
+    }
+    pattern.height = rows.len() as u32;
+    Ok(pattern)
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut plaintext = String::new();
+    if let Some(name) = &pattern.name {
+        plaintext.push_str(&format!("!Name: {}\n", name));
+    }
+    for comment in &pattern.comments {
+        plaintext.push_str(&format!("!{}\n", comment));
+    }
+
+    let mut grid = vec![vec!['.'; pattern.width as usize]; pattern.height as usize];
+    for [row, col] in &pattern.cells {
+        grid[*row as usize][*col as usize] = 'O';
+    }
+    for row in grid {
+        plaintext.extend(row);
+        plaintext.push('\n');
+    }
+    plaintext
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /** create a universe just big enough for a pattern in the plaintext `.cells` format */
+    pub fn from_plaintext(plaintext: &str) -> Result<Universe, ParseError> {
+        parse(plaintext)?.to_universe()
+    }
+
+    /** bring a `.cells` pattern to life with its top left corner at `row`, `col` */
+    pub fn load_plaintext_at(&mut self, plaintext: &str, row: u32, col: u32) -> Result<(), ParseError> {
+        parse(plaintext)?.place(self, row, col)
+    }
+
+    pub fn to_plaintext(&self) -> String {
+        write(&Pattern::from_universe(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{formats::{ParseError, plaintext::{parse, write}}, universe::Universe};
+
+    const GLIDER: &str = "!Name: Glider
+!The smallest, most common, and first discovered spaceship.
+.O.
+..O
+OOO
+";
+
+    #[test]
+    fn test_parse() {
+        let pattern = parse(GLIDER).unwrap();
+        assert_eq!(pattern.name, Some("Glider".to_string()));
+        assert_eq!(pattern.comments.len(), 1);
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(pattern.cells, vec![[0, 1], [1, 2], [2, 0], [2, 1], [2, 2]]);
+    }
+
+    #[test]
+    fn test_parse_ragged_rows() {
+        // trailing dead cells and whole empty rows may be left out
+        let pattern = parse("O\n\n..O\n\n").unwrap();
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(pattern.cells, vec![[0, 0], [2, 2]]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse(".O.\n.o."), Err(ParseError::UnexpectedCharacter { line: 2, character: 'o' }));
+    }
+
+    #[test]
+    fn test_write() {
+        let pattern = parse(GLIDER).unwrap();
+        assert_eq!(write(&pattern), GLIDER);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut universe = Universe::new(4, 3);
+        universe.init_cells(vec![[0, 3], [2, 0], [2, 1]]);
+        let restored = Universe::from_plaintext(&universe.to_plaintext()).unwrap();
+        assert_eq!((restored.width(), restored.height()), (4, 3));
+        assert_eq!(restored.cells_to_arr(), universe.cells_to_arr());
+    }
+
+    #[test]
+    fn test_load_plaintext_at() {
+        let mut universe = Universe::new(5, 5);
+        universe.load_plaintext_at(GLIDER, 1, 2).unwrap();
+        let mut expected = Universe::new(5, 5);
+        expected.init_cells(vec![[1, 3], [2, 4], [3, 2], [3, 3], [3, 4]]);
+        assert_eq!(universe.cells_to_arr(), expected.cells_to_arr());
+    }
+}
@@ -0,0 +1,384 @@
+use std::convert::TryFrom;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{formats::{ParseError, Pattern}, universe::Universe};
+
+/** lines written by `write` never exceed this many characters */
+pub const LINE_LENGTH: usize = 70;
+
+/** the most states a single letter can stand for, `A` to `X` */
+const LETTERS: u32 = 24;
+
+/**
+ * parse a pattern in the run length encoded format
+ *
+ * ```text
+ * #N Glider
+ * #C The smallest spaceship
+ * x = 3, y = 3, rule = B3/S23
+ * bob$2bo$3o!
+ * ```
+ *
+ * patterns of rules with more states write dead cells as `.`, states 1 to 24 as `A` to `X`
+ * and higher states as `pA` to `yO`, like golly does
+ */
+pub fn parse(rle: &str) -> Result<Pattern, ParseError> {
+    let mut pattern = Pattern::default();
+    let mut header_seen = false;
+    let (mut row, mut col) = (0u32, 0u32);
+    let mut count: Option<u32> = None;
+    let mut prefix: Option<char> = None;
+    let mut multistate = false;
+
+    'lines: for (index, line) in rle.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if let Some(comment) = line.strip_prefix('#') {
+            parse_comment(&mut pattern, comment);
+            continue;
+        }
+        if !header_seen {
+            if line.is_empty() { continue }
+            parse_header(&mut pattern, line)?;
+            header_seen = true;
+            continue;
+        }
+
+        for character in line.chars() {
+            let unexpected = ParseError::UnexpectedCharacter { line: line_number, character };
+            let state = match (prefix.take(), character) {
+                (Some(high), 'A'..='X') => {
+                    let state = (high as u32 - 'p' as u32 + 1) * LETTERS + (character as u32 - 'A' as u32 + 1);
+                    Some(u8::try_from(state).map_err(|_| unexpected)?)
+                }
+                (Some(_), _) => return Err(unexpected),
+                (None, '0'..='9') => {
+                    let digit = character as u32 - '0' as u32;
+                    let extended = count.unwrap_or(0).checked_mul(10).and_then(|count| count.checked_add(digit));
+                    count = Some(extended.ok_or(ParseError::InvalidCount { line: line_number })?);
+                    None
+                }
+                (None, 'p'..='y') => {
+                    prefix = Some(character);
+                    None
+                }
+                (None, 'b') => Some(0),
+                (None, 'o') => Some(1),
+                (None, '.') => Some(0),
+                (None, 'A'..='X') => Some((character as u32 - 'A' as u32 + 1) as u8),
+                (None, '$') => {
+                    row = row.saturating_add(count.take().unwrap_or(1));
+                    col = 0;
+                    None
+                }
+                (None, '!') => break 'lines,
+                (None, whitespace) if whitespace.is_whitespace() => None,
+                (None, _) => return Err(unexpected),
+            };
+
+            if let Some(state) = state {
+                multistate |= !matches!(character, 'b' | 'o');
+                let run = count.take().unwrap_or(1);
+                let end = col.checked_add(run).filter(|end| *end <= pattern.width);
+                let end = end.ok_or(ParseError::ExceedsHeader { line: line_number })?;
+                if state != 0 {
+                    if row >= pattern.height {
+                        return Err(ParseError::ExceedsHeader { line: line_number });
+                    }
+                    pattern.cells.extend((col..end).map(|col| [row, col]));
+                    pattern.states.resize(pattern.cells.len(), state);
+                }
+                col = end;
+            }
+        }
+    }
+
+    if !header_seen {
+        return Err(ParseError::MissingHeader);
+    }
+    if !multistate {
+        pattern.states.clear();
+    }
+    Ok(pattern)
+}
+
+/** write a pattern in the run length encoded format, wrapping lines at 70 characters */
+pub fn write(pattern: &Pattern) -> String {
+    let mut rle = String::new();
+    if let Some(name) = &pattern.name {
+        rle.push_str(&format!("#N {}\n", name));
+    }
+    for comment in &pattern.comments {
+        rle.push_str(&format!("#C {}\n", comment));
+    }
+    rle.push_str(&format!("x = {}, y = {}", pattern.width, pattern.height));
+    if let Some(rule) = &pattern.rule {
+        rle.push_str(&format!(", rule = {}", rule));
+    }
+    rle.push('\n');
+
+    let multistate = !pattern.states.is_empty();
+    let dead = if multistate { "." } else { "b" };
+    let mut rows = vec![vec![]; pattern.height as usize];
+    for (index, [row, col]) in pattern.cells.iter().enumerate() {
+        // dead cells are written as the gaps between the others
+        match pattern.state(index).state() {
+            0 => {}
+            state => rows[*row as usize].push((*col, state)),
+        }
+    }
+
+    let mut tokens = vec![];
+    let mut last_row = 0;
+    for (row, cols) in rows.iter_mut().enumerate().filter(|(_, cols)| !cols.is_empty()) {
+        cols.sort_unstable();
+        cols.dedup_by_key(|(col, _)| *col);
+        if row > last_row {
+            tokens.push(run(row - last_row, "$"));
+        }
+        last_row = row;
+
+        let mut col = 0;
+        let mut runs = cols.iter().peekable();
+        while let Some((start, state)) = runs.next() {
+            let mut end = start + 1;
+            while runs.peek() == Some(&&(end, *state)) {
+                runs.next();
+                end += 1;
+            }
+            if *start > col {
+                tokens.push(run((start - col) as usize, dead));
+            }
+            let tag = if multistate { state_tag(*state) } else { "o".to_string() };
+            tokens.push(run((end - start) as usize, &tag));
+            col = end;
+        }
+    }
+    tokens.push("!".to_string());
+
+    let mut line_length = 0;
+    for token in tokens {
+        if line_length + token.len() > LINE_LENGTH {
+            rle.push('\n');
+            line_length = 0;
+        }
+        line_length += token.len();
+        rle.push_str(&token);
+    }
+    rle.push('\n');
+    rle
+}
+
+fn run(length: usize, tag: &str) -> String {
+    if length == 1 { tag.to_string() } else { format!("{}{}", length, tag) }
+}
+
+/** `A` for state 1 up to `yO` for state 255 */
+fn state_tag(state: u8) -> String {
+    let (high, low) = ((state as u32 - 1) / LETTERS, (state as u32 - 1) % LETTERS);
+    let letter = char::from(b'A' + low as u8);
+    match high {
+        0 => letter.to_string(),
+        high => format!("{}{}", char::from(b'p' + high as u8 - 1), letter),
+    }
+}
+
+fn parse_comment(pattern: &mut Pattern, comment: &str) {
+    let mut chars = comment.chars();
+    let kind = chars.next();
+    let text = chars.as_str().trim().to_string();
+    match kind {
+        Some('N') => pattern.name = Some(text),
+        Some('C') | Some('c') => pattern.comments.push(text),
+        _ => {}
+    }
+}
+
+/** parse the `x = 3, y = 3, rule = B3/S23` line */
+fn parse_header(pattern: &mut Pattern, line: &str) -> Result<(), ParseError> {
+    let invalid = || ParseError::InvalidHeader(line.to_string());
+    if !line.contains('=') {
+        return Err(ParseError::MissingHeader);
+    }
+
+    // the rule comes last and may have commas of its own, as in `rule = R2,C0,M0,S2..3,B3,NN`
+    let (sizes, rule) = match line.find("rule") {
+        Some(start) => (&line[..start], Some(&line[start..])),
+        None => (line, None),
+    };
+    if let Some(rule) = rule {
+        let (_, value) = rule.split_once('=').ok_or_else(invalid)?;
+        pattern.rule = Some(value.parse()?);
+    }
+
+    let (mut width, mut height) = (None, None);
+    for field in sizes.split(',').filter(|field| !field.trim().is_empty()) {
+        let (key, value) = field.split_once('=').ok_or_else(invalid)?;
+        let value = value.trim();
+        match key.trim() {
+            "x" => width = Some(value.parse().map_err(|_| invalid())?),
+            "y" => height = Some(value.parse().map_err(|_| invalid())?),
+            _ => {}
+        }
+    }
+
+    pattern.width = width.ok_or_else(invalid)?;
+    pattern.height = height.ok_or_else(invalid)?;
+    Ok(())
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /** create a universe sized and ruled after a run length encoded pattern, e.g. pasted from LifeWiki */
+    pub fn from_rle(rle: &str) -> Result<Universe, ParseError> {
+        parse(rle)?.to_universe()
+    }
+
+    /**
+     * bring the living cells of a run length encoded pattern to life,
+     * with the top left corner of the pattern at `row`, `col`.
+     * the rule of the universe is left untouched
+     */
+    pub fn load_rle_at(&mut self, rle: &str, row: u32, col: u32) -> Result<(), ParseError> {
+        parse(rle)?.place(self, row, col)
+    }
+
+    pub fn to_rle(&self) -> String {
+        write(&Pattern::from_universe(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{formats::{ParseError, Pattern, rle::{parse, write, LINE_LENGTH}}, rules::{Rule, RuleError}, universe::Universe};
+
+    const GLIDER: &str = "#N Glider
+#O Richard K. Guy
+#C The smallest, most common, and first discovered spaceship.
+#C www.conwaylife.com/wiki/index.php?title=Glider
+x = 3, y = 3, rule = B3/S23
+bob$2bo$3o!";
+
+    #[test]
+    fn test_parse() {
+        let pattern = parse(GLIDER).unwrap();
+        assert_eq!(pattern.name, Some("Glider".to_string()));
+        assert_eq!(pattern.comments.len(), 2);
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(pattern.rule, Some(Rule::conway()));
+        assert_eq!(pattern.cells, vec![[0, 1], [1, 2], [2, 0], [2, 1], [2, 2]]);
+    }
+
+    #[test]
+    fn test_parse_multiline() {
+        // gosper glider gun, wrapped over several lines
+        let gun = "x = 36, y = 9, rule = B3/S23
+24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4b
+obo$10bo5bo7bo$11bo3bo$12b2o!";
+        let pattern = parse(gun).unwrap();
+        assert_eq!(pattern.cells.len(), 36);
+        assert!(pattern.cells.contains(&[5, 24]));
+        assert!(pattern.cells.contains(&[8, 13]));
+    }
+
+    #[test]
+    fn test_parse_rule_formats() {
+        assert_eq!(parse("x = 1, y = 1, rule = 23/36\no!").unwrap().rule, Some("B36/S23".parse().unwrap()));
+        assert_eq!(parse("x = 1, y = 1\no!").unwrap().rule, None);
+        assert_eq!(parse("x=2,y=1,rule=B3/S23\n2o!").unwrap().cells, vec![[0, 0], [0, 1]]);
+        let ranged = parse("x = 1, y = 1, rule = R2,C0,M0,S2..3,B3,NN\no!").unwrap();
+        assert_eq!(ranged.rule, Some("R2,C0,M0,S2..3,B3,NN".parse().unwrap()));
+        assert_eq!(write(&ranged), "x = 1, y = 1, rule = R2,C0,M0,S2..3,B3,NN\no!\n");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse(""), Err(ParseError::MissingHeader));
+        assert_eq!(parse("bo$2bo$3o!"), Err(ParseError::MissingHeader));
+        assert_eq!(parse("x = 3\nbo!"), Err(ParseError::InvalidHeader("x = 3".to_string())));
+        assert_eq!(parse("x = a, y = 3\nbo!"), Err(ParseError::InvalidHeader("x = a, y = 3".to_string())));
+        assert_eq!(parse("x = 3, y = 3, rule = B9/S23\nbo!"), Err(ParseError::InvalidRule(RuleError::InvalidDigit('9'))));
+        assert_eq!(parse("x = 3, y = 3\nbo$\n2bk!"), Err(ParseError::UnexpectedCharacter { line: 3, character: 'k' }));
+        assert_eq!(parse("x = 3, y = 1\n4o!"), Err(ParseError::ExceedsHeader { line: 2 }));
+        assert_eq!(parse("x = 3, y = 1\n$o!"), Err(ParseError::ExceedsHeader { line: 2 }));
+        assert_eq!(parse("x = 3, y = 1\n99999999999o!"), Err(ParseError::InvalidCount { line: 2 }));
+
+        // the header alone can ask for more cells than a universe can hold, or for none at all
+        assert_eq!(Universe::from_rle("x = 70000, y = 70000\no!").err(), Some(ParseError::TooLarge));
+        assert_eq!(Universe::from_rle("x = 0, y = 0\n!").err(), Some(ParseError::Empty));
+    }
+
+    #[test]
+    fn test_write() {
+        let mut pattern = parse(GLIDER).unwrap();
+        pattern.comments = vec![];
+        assert_eq!(write(&pattern), "#N Glider\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+
+        let sparse = Pattern { width: 5, height: 6, cells: vec![[2, 4], [5, 0], [5, 1]], ..Pattern::default() };
+        assert_eq!(write(&sparse), "x = 5, y = 6\n2$4bo3$2o!\n");
+    }
+
+    #[test]
+    fn test_multistate() {
+        let pattern = parse("x = 4, y = 2, rule = B2/S/C3\n.2A$B.pAyO!").unwrap();
+        assert_eq!(pattern.cells, vec![[0, 1], [0, 2], [1, 0], [1, 2], [1, 3]]);
+        assert_eq!(pattern.states, vec![1, 1, 2, 25, 255]);
+        assert_eq!(write(&pattern), "x = 4, y = 2, rule = B2/S/C3\n.2A$B.pAyO!\n");
+        assert_eq!(parse("x = 2, y = 1\n2A!").unwrap().states, vec![1, 1]);
+        assert!(parse("x = 2, y = 1\n2o!").unwrap().states.is_empty());
+
+        assert_eq!(parse("x = 2, y = 1\npo!"), Err(ParseError::UnexpectedCharacter { line: 2, character: 'o' }));
+        assert_eq!(parse("x = 2, y = 1\nyP!"), Err(ParseError::UnexpectedCharacter { line: 2, character: 'P' }));
+        assert_eq!(parse("x = 2, y = 1\nyX!"), Err(ParseError::UnexpectedCharacter { line: 2, character: 'X' }));
+
+        let mut universe = Universe::from_rle("x = 3, y = 1, rule = B2/S/C3\nAB!").unwrap();
+        assert_eq!(universe.cells_to_arr(), vec![1, 2, 0]);
+        assert_eq!(universe.to_rle(), "x = 3, y = 1, rule = B2/S/C3\nAB!\n");
+        universe.tick();
+        assert_eq!(universe.to_rle(), "x = 3, y = 1, rule = B2/S/C3\nB!\n");
+
+        let with_dead = Pattern { width: 3, height: 1, cells: vec![[0, 0], [0, 2]], states: vec![0, 2], ..Pattern::default() };
+        assert_eq!(write(&with_dead), "x = 3, y = 1\n2.B!\n");
+
+        assert_eq!(Universe::from_rle("x = 3, y = 1, rule = B2/S/C3\nAC!").err(), Some(ParseError::InvalidState { state: 3, states: 3 }));
+        assert_eq!(Universe::from_rle("x = 1, y = 1\nB!").err(), Some(ParseError::InvalidState { state: 2, states: 2 }));
+        let mut universe = Universe::new(4, 4);
+        assert_eq!(universe.load_rle_at("x = 2, y = 1, rule = B2/S/C3\nAB!", 0, 0), Err(ParseError::InvalidState { state: 2, states: 2 }));
+        assert_eq!(universe.cells_to_arr(), vec![0; 16]);
+    }
+
+    #[test]
+    fn test_write_wraps_lines() {
+        let mut universe = Universe::new(100, 100);
+        universe.init_cells((0..100).flat_map(|row| (0..100).filter(move |col| (row + col) % 3 == 0).map(move |col| [row, col])).collect());
+        let rle = universe.to_rle();
+        assert!(rle.lines().all(|line| line.len() <= LINE_LENGTH));
+        assert_eq!(Universe::from_rle(&rle).unwrap().cells_to_arr(), universe.cells_to_arr());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut universe = Universe::new(8, 5);
+        universe.set_rule("B36/S23").unwrap();
+        universe.init_cells(vec![[0, 7], [1, 1], [1, 2], [4, 3]]);
+
+        let rle = universe.to_rle();
+        let restored = Universe::from_rle(&rle).unwrap();
+        assert_eq!((restored.width(), restored.height()), (8, 5));
+        assert_eq!(restored.rulestring(), "B36/S23");
+        assert_eq!(restored.cells_to_arr(), universe.cells_to_arr());
+    }
+
+    #[test]
+    fn test_load_rle_at() {
+        let mut universe = Universe::new(6, 6);
+        universe.load_rle_at(GLIDER, 2, 3).unwrap();
+
+        let mut expected = Universe::new(6, 6);
+        expected.init_cells(vec![[2, 4], [3, 5], [4, 3], [4, 4], [4, 5]]);
+        assert_eq!(universe.cells_to_arr(), expected.cells_to_arr());
+
+        assert_eq!(universe.load_rle_at(GLIDER, 4, 0), Err(ParseError::DoesNotFit { width: 3, height: 3 }));
+    }
+}
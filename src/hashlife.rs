@@ -0,0 +1,684 @@
+use std::{collections::HashMap, error::Error, fmt};
+
+use wasm_bindgen::prelude::*;
+
+use crate::{cells::Cell, formats::{rle, ParseError, Pattern}, rules::{Rule, RuleError}, universe::{SizeError, Universe}};
+
+pub(crate) type NodeId = u32;
+
+pub(crate) const DEAD: NodeId = 0;
+pub(crate) const ALIVE: NodeId = 1;
+
+/** once the arena holds this many nodes, unreachable ones are dropped before the next step */
+const GARBAGE_THRESHOLD: usize = 1 << 22;
+
+/**
+ * the level of the largest root, a square of `2^63` cells centred on the origin.
+ * cells further than `2^62` rows or columns from the origin can't be addressed
+ */
+pub const MAX_LEVEL: u8 = 63;
+
+/** the largest `k` accepted by `step_pow2`, as stepping needs a root at least 3 levels above the step */
+pub const MAX_STEP: u8 = MAX_LEVEL - 3;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HashLifeError {
+    /** `step_pow2` was asked for more than `2^MAX_STEP` generations, or more than the generation counter can count */
+    StepTooLarge { k: u8 },
+    /** a cell lies, or the pattern would grow, beyond the square of the largest root */
+    OutOfRange,
+}
+
+/**
+ * a square of `2^level` x `2^level` cells
+ *
+ * level 0 nodes are single cells, every other node is made of four quadrants one level below.
+ * nodes are canonicalised, so two equal squares anywhere in space and time share one node
+ */
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    level: u8,
+    /** `[nw, ne, sw, se]` */
+    children: [NodeId; 4],
+    population: u64,
+}
+
+/**
+ * an unbounded universe stepped with Gosper's HashLife algorithm
+ *
+ * the universe is a quadtree of canonicalised nodes. for every node the result of advancing
+ * its centre by a power of two generations is memoised, so repeating patterns in space
+ * (e.g. the same still life everywhere) and in time (e.g. guns) are only ever computed once
+ */
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct HashLife {
+    nodes: Vec<Node>,
+    index: HashMap<[NodeId; 4], NodeId>,
+    /** `(node, log2 of the step) -> centre of the node after the step` */
+    results: HashMap<(NodeId, u8), NodeId>,
+    /** the empty node of every level */
+    empty: Vec<NodeId>,
+    root: NodeId,
+    /** `[row, col]` of the top left cell of the root, which is always centred on `[0, 0]` */
+    origin: [i64; 2],
+    rule: Rule,
+    generation: u64,
+}
+
+impl Default for HashLife {
+    fn default() -> Self {
+        let leaf = |population| Node { level: 0, children: [DEAD; 4], population };
+        let mut hashlife = HashLife {
+            nodes: vec![leaf(0), leaf(1)],
+            index: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![DEAD],
+            root: DEAD,
+            origin: [0, 0],
+            rule: Rule::default(),
+            generation: 0,
+        };
+        hashlife.root = hashlife.empty(3);
+        hashlife.origin = [-4, -4];
+        hashlife
+    }
+}
+
+impl HashLife {
+    pub fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    /** changing the rule invalidates every memoised result */
+    pub fn replace_rule(&mut self, rule: Rule) -> Result<(), RuleError> {
+        rule.check_unbounded()?;
+        rule.check_life_like()?;
+        self.rule = rule;
+        self.results.clear();
+        Ok(())
+    }
+
+    /** copy the living cells and the rule of a universe, whose rule has to pass the checks of `replace_rule` */
+    pub fn from_universe(universe: &Universe) -> Result<HashLife, RuleError> {
+        let mut hashlife = HashLife::default();
+        hashlife.replace_rule(universe.rule().clone())?;
+        hashlife.insert_pattern(&Pattern::from_universe(universe), 0, 0)
+            .expect("a universe always fits into the largest root");
+        Ok(hashlife)
+    }
+
+    /** bring the living cells of a pattern to life with its top left corner at `row`, `col` */
+    pub fn insert_pattern(&mut self, pattern: &Pattern, row: i64, col: i64) -> Result<(), HashLifeError> {
+        for [r, c] in &pattern.cells {
+            let row = row.checked_add(*r as i64).ok_or(HashLifeError::OutOfRange)?;
+            let col = col.checked_add(*c as i64).ok_or(HashLifeError::OutOfRange)?;
+            self.set_cell(row, col, true)?;
+        }
+        Ok(())
+    }
+
+    /** the smallest `[top, left, bottom, right]` rectangle containing every living cell */
+    pub fn bounding_box(&self) -> Option<[i64; 4]> {
+        self.bounds(self.root, self.origin[0], self.origin[1])
+    }
+
+    /** the living cells within `height` rows and `width` columns from `top`, `left` as sorted `[row, col]` */
+    pub fn living_cells_in(&self, top: i64, left: i64, width: u32, height: u32) -> Vec<[i64; 2]> {
+        let mut living = vec![];
+        let region = [top, left, top.saturating_add(height as i64), left.saturating_add(width as i64)];
+        self.collect(self.root, self.origin[0], self.origin[1], region, &mut living);
+        living.sort_unstable();
+        living
+    }
+
+    pub fn level(&self) -> u8 {
+        self.nodes[self.root as usize].level
+    }
+
+    /** the root node together with the `[row, col]` of its top left cell */
+    pub(crate) fn root(&self) -> (NodeId, [i64; 2]) {
+        (self.root, self.origin)
+    }
+
+    /**
+     * replace the whole universe with a node of at least level 3,
+     * keeping the centre of the root at the origin
+     */
+    pub(crate) fn set_root(&mut self, root: NodeId) {
+        let half = 1i64 << (self.node_level(root) - 1);
+        self.root = root;
+        self.origin = [-half, -half];
+        self.shrink();
+    }
+
+    pub(crate) fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    pub(crate) fn node_level(&self, node: NodeId) -> u8 {
+        self.nodes[node as usize].level
+    }
+
+    pub(crate) fn node_population(&self, node: NodeId) -> u64 {
+        self.nodes[node as usize].population
+    }
+
+    /** `[nw, ne, sw, se]` of a node above level 0 */
+    pub(crate) fn children(&self, node: NodeId) -> [NodeId; 4] {
+        self.nodes[node as usize].children
+    }
+
+    /** the canonical node made of four quadrants of the same level */
+    pub(crate) fn join(&mut self, children: [NodeId; 4]) -> NodeId {
+        if let Some(&node) = self.index.get(&children) {
+            return node;
+        }
+        let level = self.nodes[children[0] as usize].level + 1;
+        let population = children.iter()
+            .fold(0u64, |sum, child| sum.saturating_add(self.nodes[*child as usize].population));
+        let node = self.nodes.len() as NodeId;
+        self.nodes.push(Node { level, children, population });
+        self.index.insert(children, node);
+        node
+    }
+
+    /** the empty node of a level */
+    pub(crate) fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let below = *self.empty.last().unwrap();
+            let node = self.join([below; 4]);
+            self.empty.push(node);
+        }
+        self.empty[level as usize]
+    }
+
+    /** drop every node no longer reachable from the root, together with the memoised results */
+    pub fn collect_garbage(&mut self) {
+        let mut compacted = HashLife {
+            rule: self.rule.clone(),
+            generation: self.generation,
+            origin: self.origin,
+            ..HashLife::default()
+        };
+        let mut moved = HashMap::new();
+        compacted.root = compacted.copy_node(self, self.root, &mut moved);
+        *self = compacted;
+    }
+
+    fn copy_node(&mut self, from: &HashLife, node: NodeId, moved: &mut HashMap<NodeId, NodeId>) -> NodeId {
+        if node == DEAD || node == ALIVE {
+            return node;
+        }
+        if let Some(&copied) = moved.get(&node) {
+            return copied;
+        }
+        let mut children = from.nodes[node as usize].children;
+        for child in children.iter_mut() {
+            *child = self.copy_node(from, *child, moved);
+        }
+        let copied = self.join(children);
+        moved.insert(node, copied);
+        copied
+    }
+
+    fn centre(&mut self, node: NodeId) -> NodeId {
+        let [nw, ne, sw, se] = self.children(node);
+        self.join([self.children(nw)[3], self.children(ne)[2], self.children(sw)[1], self.children(se)[0]])
+    }
+
+    /** surround the root with empty space, doubling its size while keeping it centred */
+    fn expand(&mut self) -> Result<(), HashLifeError> {
+        let level = self.level();
+        if level >= MAX_LEVEL {
+            return Err(HashLifeError::OutOfRange);
+        }
+        let empty = self.empty(level - 1);
+        let [nw, ne, sw, se] = self.children(self.root);
+        let children = [
+            self.join([empty, empty, empty, nw]),
+            self.join([empty, empty, ne, empty]),
+            self.join([empty, sw, empty, empty]),
+            self.join([se, empty, empty, empty]),
+        ];
+        self.root = self.join(children);
+        let half = 1i64 << (level - 1);
+        self.origin = [self.origin[0] - half, self.origin[1] - half];
+        Ok(())
+    }
+
+    /** expand the root until a step of `2^k` generations can't reach beyond it */
+    fn pad(&mut self, k: u8) -> Result<(), HashLifeError> {
+        // the pattern grows by at most one cell per generation, it has to stay within the centre
+        while self.level() < k + 2 || !self.is_padded() {
+            self.expand()?;
+        }
+        self.expand()
+    }
+
+    /** whether every living cell lies within the centre half of the root */
+    fn is_padded(&self) -> bool {
+        let [nw, ne, sw, se] = self.children(self.root);
+        let population = |node: NodeId| self.nodes[node as usize].population;
+        population(nw) == population(self.children(nw)[3])
+            && population(ne) == population(self.children(ne)[2])
+            && population(sw) == population(self.children(sw)[1])
+            && population(se) == population(self.children(se)[0])
+    }
+
+    /** undo superfluous expansions to keep the root as small as the pattern allows */
+    fn shrink(&mut self) {
+        while self.level() > 3 && self.is_padded() {
+            let level = self.level();
+            self.root = self.centre(self.root);
+            let quarter = 1i64 << (level - 2);
+            self.origin = [self.origin[0] + quarter, self.origin[1] + quarter];
+        }
+    }
+
+    fn contains(&self, row: i64, col: i64) -> bool {
+        let size = 1i128 << self.level();
+        let inside = |coord: i64, origin: i64| coord as i128 >= origin as i128 && (coord as i128) < origin as i128 + size;
+        inside(row, self.origin[0]) && inside(col, self.origin[1])
+    }
+
+    fn set(&mut self, node: NodeId, row: u64, col: u64, alive: bool) -> NodeId {
+        let level = self.nodes[node as usize].level;
+        if level == 0 {
+            return if alive { ALIVE } else { DEAD };
+        }
+        let half = 1u64 << (level - 1);
+        let quadrant = (row >= half) as usize * 2 + (col >= half) as usize;
+        let mut children = self.children(node);
+        children[quadrant] = self.set(children[quadrant], row % half, col % half, alive);
+        self.join(children)
+    }
+
+    fn get(&self, node: NodeId, row: u64, col: u64) -> bool {
+        let node = &self.nodes[node as usize];
+        if node.population == 0 {
+            return false;
+        }
+        if node.level == 0 {
+            return true;
+        }
+        let half = 1u64 << (node.level - 1);
+        let quadrant = (row >= half) as usize * 2 + (col >= half) as usize;
+        self.get(node.children[quadrant], row % half, col % half)
+    }
+
+    /**
+     * the centre of a node of level `n`, advanced by `2^step` generations
+     * with `step <= n - 2`. the result is a node of level `n - 1`
+     */
+    fn successor(&mut self, node: NodeId, step: u8) -> NodeId {
+        let level = self.nodes[node as usize].level;
+        if self.nodes[node as usize].population == 0 {
+            return self.empty(level - 1);
+        }
+        if let Some(&result) = self.results.get(&(node, step)) {
+            return result;
+        }
+
+        let result = if level == 2 {
+            self.base_case(node)
+        } else {
+            let [nw, ne, sw, se] = self.children(node);
+            let [_, nw_ne, nw_sw, nw_se] = self.children(nw);
+            let [ne_nw, _, ne_sw, ne_se] = self.children(ne);
+            let [sw_nw, sw_ne, _, sw_se] = self.children(sw);
+            let [se_nw, se_ne, se_sw, _] = self.children(se);
+
+            // nine overlapping squares one level below, covering the node in a 3x3 arrangement
+            let squares = [
+                nw,
+                self.join([nw_ne, ne_nw, nw_se, ne_sw]),
+                ne,
+                self.join([nw_sw, nw_se, sw_nw, sw_ne]),
+                self.join([nw_se, ne_sw, sw_ne, se_nw]),
+                self.join([ne_sw, ne_se, se_nw, se_ne]),
+                sw,
+                self.join([sw_ne, se_nw, sw_se, se_sw]),
+                se,
+            ];
+
+            // the first half of the step, or the whole step if it is shorter than the node allows
+            let first_step = if step == level - 2 { step - 1 } else { step };
+            let mut advanced = [DEAD; 9];
+            for (result, square) in advanced.iter_mut().zip(squares) {
+                *result = self.successor(square, first_step);
+            }
+
+            let [c00, c01, c02, c10, c11, c12, c20, c21, c22] = advanced;
+            let quadrants = [[c00, c01, c10, c11], [c01, c02, c11, c12], [c10, c11, c20, c21], [c11, c12, c21, c22]];
+            let mut result = [DEAD; 4];
+            for (result, quadrant) in result.iter_mut().zip(quadrants) {
+                let joined = self.join(quadrant);
+                *result = if step == level - 2 {
+                    // the second half of the step
+                    self.successor(joined, step - 1)
+                } else {
+                    self.centre(joined)
+                };
+            }
+            self.join(result)
+        };
+
+        self.results.insert((node, step), result);
+        result
+    }
+
+    /** advance the centre 2x2 of a 4x4 node by one generation */
+    fn base_case(&mut self, node: NodeId) -> NodeId {
+        let mut grid = [[false; 4]; 4];
+        for (row, cells) in grid.iter_mut().enumerate() {
+            for (col, cell) in cells.iter_mut().enumerate() {
+                *cell = self.get(node, row as u64, col as u64);
+            }
+        }
+
+        let mut next = [DEAD; 4];
+        for (quadrant, next) in next.iter_mut().enumerate() {
+            let (row, col) = (1 + quadrant / 2, 1 + quadrant % 2);
+            let living_neightbours = grid[row - 1..=row + 1].iter()
+                .flat_map(|cells| &cells[col - 1..=col + 1])
+                .filter(|alive| **alive)
+                .count() as u8 - grid[row][col] as u8;
+            let cell = if grid[row][col] { Cell::Alive } else { Cell::Dead };
+            *next = if self.rule.next_state(cell, living_neightbours.into()).is_alive() { ALIVE } else { DEAD };
+        }
+        self.join(next)
+    }
+
+    fn bounds(&self, node: NodeId, top: i64, left: i64) -> Option<[i64; 4]> {
+        let node = &self.nodes[node as usize];
+        if node.population == 0 {
+            return None;
+        }
+        if node.level == 0 {
+            return Some([top, left, top, left]);
+        }
+        let half = 1i64 << (node.level - 1);
+        let offsets = [(0, 0), (0, half), (half, 0), (half, half)];
+        node.children.iter().zip(offsets)
+            .filter_map(|(child, (row, col))| self.bounds(*child, top + row, left + col))
+            .reduce(|a, b| [a[0].min(b[0]), a[1].min(b[1]), a[2].max(b[2]), a[3].max(b[3])])
+    }
+
+    fn collect(&self, node: NodeId, top: i64, left: i64, region: [i64; 4], living: &mut Vec<[i64; 2]>) {
+        let node = &self.nodes[node as usize];
+        let size = 1i128 << node.level;
+        let overlaps = (top as i128) < region[2] as i128 && top as i128 + size > region[0] as i128
+            && (left as i128) < region[3] as i128 && left as i128 + size > region[1] as i128;
+        if node.population == 0 || !overlaps {
+            return;
+        }
+        if node.level == 0 {
+            living.push([top, left]);
+            return;
+        }
+        let half = 1i64 << (node.level - 1);
+        let offsets = [(0, 0), (0, half), (half, 0), (half, half)];
+        for (child, (row, col)) in node.children.iter().zip(offsets) {
+            self.collect(*child, top + row, left + col, region, living);
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl HashLife {
+    pub fn new() -> HashLife {
+        HashLife::default()
+    }
+
+    /** replace the rule used for stepping, rules which are not life-like or give birth on 0 neightbours are rejected */
+    pub fn set_rule(&mut self, rulestring: &str) -> Result<(), RuleError> {
+        self.replace_rule(rulestring.parse()?)
+    }
+
+    pub fn rulestring(&self) -> String {
+        self.rule.to_string()
+    }
+
+    /** bring a run length encoded pattern to life with its top left corner at `row`, `col` */
+    pub fn load_rle_at(&mut self, rle: &str, row: i64, col: i64) -> Result<(), ParseError> {
+        let pattern = rle::parse(rle)?;
+        pattern.check_states(&self.rule)?;
+        self.insert_pattern(&pattern, row, col).map_err(|_| ParseError::TooLarge)
+    }
+
+    pub fn set_cell(&mut self, row: i64, col: i64, alive: bool) -> Result<(), HashLifeError> {
+        while !self.contains(row, col) {
+            self.expand()?;
+        }
+        let [top, left] = self.origin;
+        self.root = self.set(self.root, (row - top) as u64, (col - left) as u64, alive);
+        Ok(())
+    }
+
+    pub fn get_cell(&self, row: i64, col: i64) -> bool {
+        let [top, left] = self.origin;
+        self.contains(row, col) && self.get(self.root, (row - top) as u64, (col - left) as u64)
+    }
+
+    /** the number of living cells, read from the root without visiting the tree */
+    pub fn population(&self) -> u64 {
+        self.nodes[self.root as usize].population
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /** the number of distinct nodes currently held in memory */
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /**
+     * advance the universe by `2^k` generations at once, for `k` up to `MAX_STEP`.
+     * nothing changes if the pattern could grow beyond the largest root on the way
+     */
+    pub fn step_pow2(&mut self, k: u8) -> Result<(), HashLifeError> {
+        let generation = match k {
+            0..=MAX_STEP => self.generation.checked_add(1 << k),
+            _ => None,
+        };
+        let generation = generation.ok_or(HashLifeError::StepTooLarge { k })?;
+        if self.nodes.len() > GARBAGE_THRESHOLD {
+            self.collect_garbage();
+        }
+        let (root, origin) = (self.root, self.origin);
+        if let Err(err) = self.pad(k) {
+            (self.root, self.origin) = (root, origin);
+            return Err(err);
+        }
+
+        let level = self.level();
+        self.root = self.successor(self.root, k);
+        let quarter = 1i64 << (level - 2);
+        self.origin = [self.origin[0] + quarter, self.origin[1] + quarter];
+        self.generation = generation;
+        self.shrink();
+        Ok(())
+    }
+
+    /** advance the universe by any number of generations, one power of two at a time */
+    pub fn step(&mut self, generations: u64) -> Result<(), HashLifeError> {
+        for k in 0..MAX_STEP {
+            if generations >> k & 1 == 1 {
+                self.step_pow2(k)?;
+            }
+        }
+        // the rest is a multiple of the largest step
+        for _ in 0..generations >> MAX_STEP {
+            self.step_pow2(MAX_STEP)?;
+        }
+        Ok(())
+    }
+
+    /** copy the `width` x `height` region whose top left corner is at `top`, `left` into a dense universe */
+    pub fn to_universe(&self, top: i64, left: i64, width: u32, height: u32) -> Result<Universe, SizeError> {
+        let mut universe = Universe::try_new(width, height)?;
+        universe.replace_rule(self.rule.clone());
+        let living = self.living_cells_in(top, left, width, height);
+        universe.init_cells(living.into_iter().map(|[row, col]| [(row - top) as u32, (col - left) as u32]).collect());
+        Ok(universe)
+    }
+}
+
+impl fmt::Display for HashLifeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HashLifeError::StepTooLarge { k } => write!(f, "can't step 2^{} generations at once, at most 2^{}", k, MAX_STEP),
+            HashLifeError::OutOfRange => write!(f, "the pattern reaches beyond {} cells from the origin", 1u64 << (MAX_LEVEL - 1)),
+        }
+    }
+}
+
+impl Error for HashLifeError {}
+
+impl From<HashLifeError> for JsValue {
+    fn from(err: HashLifeError) -> Self {
+        js_sys::Error::new(&err.to_string()).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{hashlife::{HashLife, HashLifeError, MAX_LEVEL, MAX_STEP}, random::soup, rules::RuleError, sparse::SparseUniverse, universe::{SizeError, Universe}};
+
+    const GLIDER: &str = "x = 3, y = 3\nbo$2bo$3o!";
+    const GOSPER_GUN: &str = "x = 36, y = 9, rule = B3/S23
+24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4b
+obo$10bo5bo7bo$11bo3bo$12b2o!";
+
+    #[test]
+    fn test_set_cell() {
+        let mut hashlife = HashLife::new();
+        hashlife.set_cell(-100, 3, true).unwrap();
+        hashlife.set_cell(7, 1 << 40, true).unwrap();
+        assert!(hashlife.get_cell(-100, 3));
+        assert!(hashlife.get_cell(7, 1 << 40));
+        assert!(!hashlife.get_cell(7, 0));
+        assert_eq!(hashlife.population(), 2);
+        assert_eq!(hashlife.bounding_box(), Some([-100, 3, 7, 1 << 40]));
+
+        hashlife.set_cell(-100, 3, false).unwrap();
+        assert_eq!(hashlife.population(), 1);
+    }
+
+    #[test]
+    fn test_matches_sparse() {
+        let mut sparse = SparseUniverse::new();
+        let mut hashlife = HashLife::new();
+        for [row, col] in soup(13, 32, 32, 1.0 / 3.0) {
+            let (row, col) = (row as i64 - 16, col as i64 - 16);
+            sparse.set_cell(row, col, true);
+            hashlife.set_cell(row, col, true).unwrap();
+        }
+
+        // steps of 1, 2, 4 ... generations all agree with plain stepping
+        for k in 0..6 {
+            hashlife.step_pow2(k).unwrap();
+            for _ in 0..1 << k {
+                sparse.next_epoch();
+            }
+            assert_eq!(hashlife.generation(), sparse.generation());
+            assert_eq!(hashlife.population(), sparse.population());
+            let [top, left, bottom, right] = sparse.bounding_box().unwrap();
+            assert_eq!(hashlife.bounding_box(), sparse.bounding_box());
+            assert_eq!(
+                hashlife.living_cells_in(top, left, (right - left + 1) as u32, (bottom - top + 1) as u32),
+                sparse.living_cells()
+            );
+        }
+    }
+
+    #[test]
+    fn test_glider_far_away() {
+        let mut hashlife = HashLife::new();
+        hashlife.load_rle_at(GLIDER, 0, 0).unwrap();
+        // a glider moves one cell diagonally every 4 generations
+        hashlife.step_pow2(42).unwrap();
+        let distance = 1i64 << 40;
+        assert_eq!(hashlife.generation(), 1 << 42);
+        assert_eq!(hashlife.population(), 5);
+        assert_eq!(hashlife.bounding_box(), Some([distance, distance, distance + 2, distance + 2]));
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut hashlife = HashLife::new();
+        hashlife.load_rle_at(GLIDER, 0, 0).unwrap();
+        assert_eq!(hashlife.step_pow2(MAX_STEP + 1), Err(HashLifeError::StepTooLarge { k: MAX_STEP + 1 }));
+        assert_eq!(hashlife.step_pow2(u8::MAX), Err(HashLifeError::StepTooLarge { k: u8::MAX }));
+        assert_eq!(hashlife.generation(), 0);
+        assert_eq!(hashlife.set_cell(i64::MAX, 0, true), Err(HashLifeError::OutOfRange));
+
+        // a cell at the edge of the largest root leaves no room to step, and the universe is left as it was
+        hashlife.set_cell(-1 << 62, 0, true).unwrap();
+        assert_eq!(hashlife.level(), MAX_LEVEL);
+        assert_eq!(hashlife.step(1), Err(HashLifeError::OutOfRange));
+        assert_eq!(hashlife.population(), 6);
+        assert_eq!(hashlife.bounding_box(), Some([-1 << 62, 0, 2, 2]));
+    }
+
+    #[test]
+    fn test_gun_population() {
+        // the gun emits a glider every 30 generations, which keeps flying away
+        let mut hashlife = HashLife::new();
+        hashlife.load_rle_at(GOSPER_GUN, 0, 0).unwrap();
+        hashlife.step(30 * 1000).unwrap();
+        assert_eq!(hashlife.population(), 36 + 5 * 1000);
+        hashlife.step_pow2(30).unwrap();
+        assert!(hashlife.population() > 5 * 35_000_000);
+    }
+
+    #[test]
+    fn test_to_universe() {
+        let mut hashlife = HashLife::new();
+        hashlife.load_rle_at(GLIDER, -1, -1).unwrap();
+        hashlife.step(4).unwrap();
+
+        let universe = hashlife.to_universe(0, 0, 3, 3).unwrap();
+        let mut expected = Universe::new(3, 3);
+        expected.init_cells(vec![[0, 1], [1, 2], [2, 0], [2, 1], [2, 2]]);
+        assert_eq!(universe.cells_to_arr(), expected.cells_to_arr());
+
+        let round_trip = HashLife::from_universe(&universe).unwrap();
+        assert_eq!(round_trip.population(), 5);
+        assert!(round_trip.get_cell(2, 2));
+
+        assert_eq!(hashlife.to_universe(0, 0, 0, 3).err(), Some(SizeError { width: 0, height: 3 }));
+        assert!(hashlife.to_universe(i64::MAX, i64::MAX, u32::MAX, u32::MAX).is_err());
+        let mut generations = Universe::new(3, 3);
+        generations.set_rule("B2/S/C3").unwrap();
+        assert!(matches!(HashLife::from_universe(&generations), Err(RuleError::Unsupported(_))));
+    }
+
+    #[test]
+    fn test_collect_garbage() {
+        let mut hashlife = HashLife::new();
+        hashlife.load_rle_at(GOSPER_GUN, 0, 0).unwrap();
+        hashlife.step(1000).unwrap();
+        let population = hashlife.population();
+        let nodes = hashlife.node_count();
+
+        hashlife.collect_garbage();
+        assert!(hashlife.node_count() < nodes);
+        assert_eq!(hashlife.population(), population);
+        assert_eq!(hashlife.generation(), 1000);
+        hashlife.step(30).unwrap();
+        assert_eq!(hashlife.population(), population + 5);
+    }
+
+    #[test]
+    fn test_rule() {
+        let mut hashlife = HashLife::new();
+        assert!(matches!(hashlife.set_rule("B0/S8"), Err(RuleError::Unsupported(_))));
+        assert!(matches!(hashlife.set_rule("B2/S/C3"), Err(RuleError::Unsupported(_))));
+        hashlife.set_rule("B36/S23").unwrap();
+        assert_eq!(hashlife.rulestring(), "B36/S23");
+    }
+}
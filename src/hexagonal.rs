@@ -0,0 +1,214 @@
+use std::ops::Range;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{topology::Topology, universe::{EditError, Universe}};
+
+/**
+ * universes with a hexagonal rule store the lattice in axial coordinates, like golly:
+ * cell `row`, `col` touches `row`, `col ± 1`, `row - 1`, `col - 1 ..= col` and `row + 1`, `col ..= col + 1`,
+ * so every row is drawn half a hexagon to the left of the row above it.
+ * offset coordinates instead number the hexagons of each drawn row from the left edge of a rectangle,
+ * with the even rows pushed half a hexagon to the right
+ */
+pub fn offset_to_axial(row: i64, col: i64) -> (i64, i64) {
+    (row, col + row.div_euclid(2))
+}
+
+pub fn axial_to_offset(row: i64, col: i64) -> (i64, i64) {
+    (row, col - row.div_euclid(2))
+}
+
+/**
+ * where the hexagons of a hexagonal universe go in a frame: `size` pixels wide,
+ * `pitch` pixels from one row to the next and reaching `reach` pixels above and below their centre.
+ * every pixel belongs to the hexagon whose centre is closest to it, so they tile the frame without overlapping
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct HexLayout {
+    size: i64,
+    pitch: i64,
+    reach: i64,
+}
+
+impl HexLayout {
+    pub(crate) fn new(size: u32) -> HexLayout {
+        let size = size.max(1) as i64;
+        // a regular hexagon is sqrt(3) / 2 times as far from the next row as it is wide
+        let pitch = ((size * 866 + 500) / 1000).max(1);
+        let reach = (4 * pitch * pitch + size * size + 8 * pitch - 1) / (8 * pitch);
+        HexLayout { size, pitch, reach }
+    }
+
+    pub(crate) fn frame_size(&self, width: u32, height: u32) -> (u64, u64) {
+        let frame_width = width as i64 * self.size + self.size / 2;
+        let frame_height = (height as i64 - 1).max(0) * self.pitch + 2 * self.reach;
+        (frame_width as u64, frame_height as u64)
+    }
+
+    /** the centre of the hexagon at offset `row`, `col`, in half pixels */
+    fn centre(&self, row: i64, col: i64) -> (i64, i64) {
+        let shift = if row.rem_euclid(2) == 0 { self.size / 2 } else { 0 };
+        (2 * (col * self.size + shift) + self.size, 2 * (row * self.pitch + self.reach))
+    }
+
+    /** the rectangle of pixels `(xs, ys)` a hexagon is drawn within */
+    pub(crate) fn bounds(&self, row: i64, col: i64) -> (Range<i64>, Range<i64>) {
+        let (x, y) = self.centre(row, col);
+        ((x - self.size) / 2 - 1..(x + self.size) / 2 + 1, (y / 2 - self.reach)..(y / 2 + self.reach))
+    }
+
+    /** whether the pixel `x`, `y` belongs to the hexagon at offset `row`, `col`, ties go to the upper left one */
+    pub(crate) fn contains(&self, row: i64, col: i64, x: i64, y: i64) -> bool {
+        let (px, py) = (2 * x + 1, 2 * y + 1);
+        let distance = |(cx, cy): (i64, i64)| (cx - px).pow(2) + (cy - py).pow(2);
+        let own = (distance(self.centre(row, col)), row, col);
+        (row - 1..=row + 1)
+            .flat_map(|r| (col - 1..=col + 1).map(move |c| (r, c)))
+            .filter(|neightbour| *neightbour != (row, col))
+            .all(|(r, c)| own < (distance(self.centre(r, c)), r, c))
+    }
+}
+
+impl Universe {
+    /**
+     * how many hexagons the drawing is moved to the right. a torus wraps every row back into the rectangle,
+     * the other topologies are drawn as the parallelogram the cells are stored in, whose lower rows reach further left
+     */
+    fn hex_shift(&self) -> i64 {
+        match self.topology() {
+            Topology::Torus => 0,
+            _ => (self.height() as i64 - 1).div_euclid(2),
+        }
+    }
+
+    /** how many hexagons wide the drawing is */
+    pub(crate) fn hex_columns(&self) -> u32 {
+        self.width() + self.hex_shift() as u32
+    }
+
+    /** the offset coordinates of the hexagon stored at `row`, `col` within the drawing */
+    pub(crate) fn hex_offset(&self, row: u32, col: u32) -> (i64, i64) {
+        let (row, col) = axial_to_offset(row as i64, col as i64);
+        match self.topology() {
+            Topology::Torus => (row, col.rem_euclid(self.width() as i64)),
+            _ => (row, col + self.hex_shift()),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /**
+     * the `[row, col]` a hexagon drawn at offset coordinates `row`, `col` is stored at,
+     * which is what `set_cell`, `set_cells` and the other editing methods take
+     */
+    pub fn offset_to_cell(&self, row: u32, col: u32) -> Result<Vec<u32>, EditError> {
+        if col >= self.hex_columns() {
+            return Err(EditError::OutOfBounds { row, col });
+        }
+        let (axial_row, axial_col) = offset_to_axial(row as i64, col as i64 - self.hex_shift());
+        let axial_col = match self.topology() {
+            Topology::Torus => axial_col.rem_euclid(self.width() as i64),
+            _ => axial_col,
+        };
+        if axial_row >= self.height() as i64 || !(0..self.width() as i64).contains(&axial_col) {
+            return Err(EditError::OutOfBounds { row, col });
+        }
+        Ok(vec![axial_row as u32, axial_col as u32])
+    }
+
+    /** the offset coordinates the hexagon stored at `row`, `col` is drawn at by `Renderer` */
+    pub fn cell_to_offset(&self, row: u32, col: u32) -> Result<Vec<u32>, EditError> {
+        if row >= self.height() || col >= self.width() {
+            return Err(EditError::OutOfBounds { row, col });
+        }
+        let (row, col) = self.hex_offset(row, col);
+        Ok(vec![row as u32, col as u32])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        hexagonal::{axial_to_offset, offset_to_axial, HexLayout},
+        neighbourhood::Neighbourhood, topology::Topology, universe::{EditError, Universe},
+    };
+
+    #[test]
+    fn test_coordinates() {
+        assert_eq!(offset_to_axial(0, 3), (0, 3));
+        assert_eq!(offset_to_axial(3, 3), (3, 4));
+        assert_eq!(offset_to_axial(-1, 0), (-1, -1));
+        for row in -5..5 {
+            for col in -5..5 {
+                let (r, c) = offset_to_axial(row, col);
+                assert_eq!(axial_to_offset(r, c), (row, col));
+            }
+        }
+
+        let mut universe = Universe::new(5, 4);
+        universe.set_rule("B2/S34H").unwrap();
+        assert_eq!(universe.offset_to_cell(3, 4), Ok(vec![3, 0]));
+        assert_eq!(universe.cell_to_offset(3, 0), Ok(vec![3, 4]));
+        assert_eq!(universe.offset_to_cell(4, 0), Err(EditError::OutOfBounds { row: 4, col: 0 }));
+        assert_eq!(universe.offset_to_cell(0, 5), Err(EditError::OutOfBounds { row: 0, col: 5 }));
+
+        // without wrapping around, the lower rows are drawn further left and the drawing is moved right to fit them
+        universe.set_topology(Topology::Bounded);
+        assert_eq!(universe.hex_columns(), 6);
+        assert_eq!(universe.cell_to_offset(3, 0), Ok(vec![3, 0]));
+        assert_eq!(universe.offset_to_cell(3, 0), Ok(vec![3, 0]));
+        assert_eq!(universe.cell_to_offset(0, 4), Ok(vec![0, 5]));
+        assert_eq!(universe.offset_to_cell(0, 0), Err(EditError::OutOfBounds { row: 0, col: 0 }));
+        assert_eq!(universe.offset_to_cell(3, 5), Err(EditError::OutOfBounds { row: 3, col: 5 }));
+
+        for topology in [Topology::Torus, Topology::Bounded, Topology::KleinBottle] {
+            universe.set_topology(topology);
+            for row in 0..4 {
+                for col in 0..5 {
+                    let offset = universe.cell_to_offset(row, col).unwrap();
+                    assert!(offset[1] < universe.hex_columns());
+                    assert_eq!(universe.offset_to_cell(offset[0], offset[1]), Ok(vec![row, col]), "{:?}", topology);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_offset_neightbours() {
+        // the six neightbours of a hexagon drawn in offset coordinates, for an even and an odd row
+        for (row, neightbours) in [(2, [(1, 2), (1, 3), (2, 1), (2, 3), (3, 2), (3, 3)]), (3, [(2, 1), (2, 2), (3, 1), (3, 3), (4, 1), (4, 2)])] {
+            let (r, c) = offset_to_axial(row, 2);
+            let mut expected: Vec<(i64, i64)> = neightbours.iter().map(|(row, col)| offset_to_axial(*row, *col)).collect();
+            let mut actual: Vec<(i64, i64)> = Neighbourhood::hexagonal(1).unwrap().cells().iter().map(|(dr, dc, _)| (r + dr, c + dc)).collect();
+            expected.sort_unstable();
+            actual.sort_unstable();
+            assert_eq!(actual, expected, "row {}", row);
+        }
+    }
+
+    #[test]
+    fn test_layout_tiles() {
+        for size in [1, 2, 5, 8, 13] {
+            let layout = HexLayout::new(size);
+            let (width, height) = layout.frame_size(4, 4);
+            for y in 0..height as i64 {
+                for x in 0..width as i64 {
+                    let owners: Vec<(i64, i64)> = (-1..5)
+                        .flat_map(|row| (-1..5).map(move |col| (row, col)))
+                        .filter(|(row, col)| layout.contains(*row, *col, x, y))
+                        .collect();
+                    assert_eq!(owners.len(), 1, "size {} pixel {}, {}", size, x, y);
+                    let (xs, ys) = layout.bounds(owners[0].0, owners[0].1);
+                    assert!(xs.contains(&x) && ys.contains(&y), "size {} pixel {}, {}", size, x, y);
+                }
+            }
+        }
+
+        let layout = HexLayout::new(8);
+        assert_eq!(layout.frame_size(3, 2), (28, 17));
+        let (xs, ys) = layout.bounds(1, 0);
+        assert!(xs.contains(&3) && ys.contains(&11) && layout.contains(1, 0, 3, 11));
+    }
+}
@@ -0,0 +1,214 @@
+use std::{collections::VecDeque, mem::size_of};
+
+use crate::cells::Cell;
+
+/** the memory the history of a new universe (and `History::default`) may use, in bytes */
+pub const DEFAULT_BUDGET: usize = 16 << 20;
+
+/** a single cell that changed, with its state before and after the change */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Change {
+    pub index: u32,
+    pub before: Cell,
+    pub after: Cell,
+}
+
+/** every cell changed by one edit or generation step */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Diff {
+    pub changes: Vec<Change>,
+    /** how many generations the change moved the universe forward, 0 for edits */
+    pub generations: i64,
+}
+
+impl Diff {
+    /** the cells which differ between two states of the same universe */
+    pub fn between(before: &[Cell], after: &[Cell]) -> Diff {
+        let changes = before.iter().zip(after).enumerate()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(index, (before, after))| Change { index: index as u32, before: *before, after: *after })
+            .collect();
+        Diff { changes, generations: 0 }
+    }
+
+    /** whether the diff neither changes a cell nor moves to another generation */
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.generations == 0
+    }
+
+    /** write the states after the change */
+    pub fn apply(&self, cells: &mut [Cell]) {
+        for change in &self.changes {
+            cells[change.index as usize] = change.after;
+        }
+    }
+
+    /** write the states before the change, last change first */
+    pub fn revert(&self, cells: &mut [Cell]) {
+        for change in self.changes.iter().rev() {
+            cells[change.index as usize] = change.before;
+        }
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        size_of::<Diff>() + self.changes.len() * size_of::<Change>()
+    }
+}
+
+/**
+ * undo and redo stacks of diffs.
+ * once the diffs take more than `budget` bytes the oldest ones are forgotten
+ */
+#[derive(Clone, Debug)]
+pub struct History {
+    undo: VecDeque<Diff>,
+    redo: Vec<Diff>,
+    budget: usize,
+    used: usize,
+}
+
+impl Default for History {
+    fn default() -> History {
+        History::with_budget(DEFAULT_BUDGET)
+    }
+}
+
+impl History {
+    pub fn with_budget(budget: usize) -> History {
+        History { undo: VecDeque::new(), redo: vec![], budget, used: 0 }
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /** change the memory budget, forgetting the oldest diffs if they no longer fit */
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.trim();
+    }
+
+    /** the memory used by the recorded diffs, in bytes */
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    /** remember a new diff, which makes everything undone so far impossible to redo */
+    pub fn record(&mut self, diff: Diff) {
+        if diff.is_empty() || self.budget == 0 {
+            return;
+        }
+        self.clear_redo();
+        self.used += diff.size();
+        self.undo.push_back(diff);
+        self.trim();
+    }
+
+    /** the diff to revert, which becomes available to `redo` */
+    pub fn undo(&mut self) -> Option<&Diff> {
+        let diff = self.undo.pop_back()?;
+        self.redo.push(diff);
+        self.redo.last()
+    }
+
+    /** the diff to apply again, which becomes available to `undo` */
+    pub fn redo(&mut self) -> Option<&Diff> {
+        let diff = self.redo.pop()?;
+        self.undo.push_back(diff);
+        self.undo.back()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.used = 0;
+    }
+
+    fn clear_redo(&mut self) {
+        self.used -= self.redo.drain(..).map(|diff| diff.size()).sum::<usize>();
+    }
+
+    fn trim(&mut self) {
+        while self.used > self.budget {
+            let Some(oldest) = self.undo.pop_front() else {
+                self.clear_redo();
+                return;
+            };
+            self.used -= oldest.size();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{cells::Cell, history::{Change, Diff, History}};
+
+    const DEAD: Cell = Cell::Dead;
+    const ALIVE: Cell = Cell::Alive;
+
+    fn flip(index: u32) -> Diff {
+        Diff { changes: vec![Change { index, before: DEAD, after: ALIVE }], generations: 0 }
+    }
+
+    #[test]
+    fn test_between() {
+        let diff = Diff::between(&[DEAD, ALIVE, ALIVE], &[ALIVE, ALIVE, DEAD]);
+        assert_eq!(diff.changes, vec![
+            Change { index: 0, before: DEAD, after: ALIVE },
+            Change { index: 2, before: ALIVE, after: DEAD },
+        ]);
+
+        let mut cells = [DEAD, ALIVE, ALIVE];
+        diff.apply(&mut cells);
+        assert_eq!(cells, [ALIVE, ALIVE, DEAD]);
+        diff.revert(&mut cells);
+        assert_eq!(cells, [DEAD, ALIVE, ALIVE]);
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut history = History::default();
+        assert!(!history.can_undo());
+        history.record(flip(0));
+        history.record(flip(1));
+        history.record(Diff::default());
+
+        assert_eq!(history.undo(), Some(&flip(1)));
+        assert!(history.can_redo());
+        assert_eq!(history.redo(), Some(&flip(1)));
+        assert_eq!(history.redo(), None);
+
+        history.undo();
+        history.record(flip(2));
+        assert!(!history.can_redo());
+        assert_eq!(history.undo(), Some(&flip(2)));
+        assert_eq!(history.undo(), Some(&flip(0)));
+        assert_eq!(history.undo(), None);
+    }
+
+    #[test]
+    fn test_budget() {
+        let size = flip(0).size();
+        let mut history = History::with_budget(2 * size);
+        for index in 0..5 {
+            history.record(flip(index));
+        }
+        assert_eq!(history.used(), 2 * size);
+        assert_eq!(history.undo(), Some(&flip(4)));
+        assert_eq!(history.undo(), Some(&flip(3)));
+        assert_eq!(history.undo(), None);
+
+        history.set_budget(0);
+        history.record(flip(0));
+        assert!(!history.can_undo() && !history.can_redo());
+        assert_eq!(history.used(), 0);
+    }
+}
@@ -1,3 +1,19 @@
+pub mod analysis;
 pub mod cells;
+pub mod formats;
+pub mod hashlife;
+pub mod hexagonal;
+pub mod history;
+pub mod neighbourhood;
+pub mod packed;
+pub mod random;
+pub mod render;
+pub mod rules;
+pub mod sparse;
+pub(crate) mod summed;
+pub mod timeline;
+pub mod topology;
 mod utils;
-pub mod universe;
\ No newline at end of file
+pub mod universe;
+pub mod viewport;
+pub mod wireworld;
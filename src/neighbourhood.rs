@@ -0,0 +1,251 @@
+use std::fmt;
+
+use crate::rules::RuleError;
+
+/** the furthest a neighbourhood can reach, as in golly */
+pub const MAX_RANGE: u32 = 500;
+
+/** which cells within the range of a neighbourhood count as neightbours */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shape {
+    /** the whole square around the cell */
+    Moore,
+    /** the diamond of cells at most `range` steps away without going diagonally */
+    VonNeumann,
+    /**
+     * the hexagon around the cell, see `hexagonal` for how the lattice is stored.
+     * within range 1 these are the moore neightbours without the top right and bottom left corners, like in golly
+     */
+    Hexagonal,
+    /** a square mask giving every cell its own weight */
+    Weighted,
+}
+
+/**
+ * the cells around a cell which count as its neightbours, and how much each of them counts.
+ * a rule is evaluated over the sum of the weights of the living neightbours,
+ * which is the number of living neightbours for every shape but `Weighted`
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Neighbourhood {
+    shape: Shape,
+    range: u32,
+    /** whether the cell counts itself, as with `M1` in golly's notation. weighted masks have their own centre */
+    centre: bool,
+    /** the `(2 * range + 1)²` weights of a weighted mask row by row, empty for the other shapes */
+    weights: Vec<i8>,
+}
+
+impl Neighbourhood {
+    /** the 8 cells of the surrounding 3x3 square, or the `(2 * range + 1)² - 1` of a larger square */
+    pub fn moore(range: u32) -> Result<Neighbourhood, RuleError> {
+        Self::shaped(Shape::Moore, range)
+    }
+
+    /** the 4 orthogonal neightbours, or every cell at most `range` orthogonal steps away */
+    pub fn von_neumann(range: u32) -> Result<Neighbourhood, RuleError> {
+        Self::shaped(Shape::VonNeumann, range)
+    }
+
+    /** the 6 cells around a hexagon, or every hexagon at most `range` steps away */
+    pub fn hexagonal(range: u32) -> Result<Neighbourhood, RuleError> {
+        Self::shaped(Shape::Hexagonal, range)
+    }
+
+    /**
+     * a square mask of weights row by row, centred on the cell (whose own weight is the middle one).
+     * a mask of 9 weights has range 1, one of 25 range 2 and so on
+     */
+    pub fn weighted(weights: &[i8]) -> Result<Neighbourhood, RuleError> {
+        let side = (1..).map(|side| 2 * side + 1).find(|side| side * side >= weights.len()).unwrap();
+        if side * side != weights.len() {
+            return Err(RuleError::InvalidNeighbourhood(format!("{} weights do not make an odd sized square", weights.len())));
+        }
+        Ok(Neighbourhood { weights: weights.to_vec(), ..Self::shaped(Shape::Weighted, side as u32 / 2)? })
+    }
+
+    fn shaped(shape: Shape, range: u32) -> Result<Neighbourhood, RuleError> {
+        if !(1..=MAX_RANGE).contains(&range) {
+            return Err(RuleError::InvalidNeighbourhood(format!("range {} is not within 1 to {}", range, MAX_RANGE)));
+        }
+        Ok(Neighbourhood { shape, range, centre: false, weights: vec![] })
+    }
+
+    /** the same neighbourhood with the cell counting itself too, unless its weighted mask says otherwise */
+    pub fn including_centre(self) -> Neighbourhood {
+        Neighbourhood { centre: self.shape != Shape::Weighted, ..self }
+    }
+
+    pub fn shape(&self) -> Shape {
+        self.shape
+    }
+
+    pub fn range(&self) -> u32 {
+        self.range
+    }
+
+    /** how much the cell `row`, `col` away counts, 0 for cells which are not neightbours */
+    pub fn weight(&self, row: i64, col: i64) -> i32 {
+        let range = self.range as i64;
+        if row.abs() > range || col.abs() > range {
+            return 0;
+        }
+        if self.shape == Shape::Weighted {
+            let side = 2 * range + 1;
+            return self.weights[((row + range) * side + col + range) as usize] as i32;
+        }
+        if (row, col) == (0, 0) {
+            return self.centre as i32;
+        }
+        let within = match self.shape {
+            Shape::VonNeumann => row.abs() + col.abs() <= range,
+            Shape::Hexagonal => (row - col).abs() <= range,
+            _ => true,
+        };
+        within as i32
+    }
+
+    /** the `(row, col, weight)` offset of every cell with a non zero weight */
+    pub fn cells(&self) -> Vec<(i64, i64, i32)> {
+        let range = self.range as i64;
+        (-range..=range)
+            .flat_map(|row| (-range..=range).map(move |col| (row, col)))
+            .map(|(row, col)| (row, col, self.weight(row, col)))
+            .filter(|(_, _, weight)| *weight != 0)
+            .collect()
+    }
+
+    /** the largest weighted sum the living neightbours can reach */
+    pub fn max_count(&self) -> u32 {
+        self.cells().iter().map(|(_, _, weight)| (*weight).max(0) as u32).sum()
+    }
+
+    /** the letter appended to `B../S..` rulestrings using this neighbourhood, if they can describe it */
+    pub fn suffix(&self) -> Option<&'static str> {
+        match self.shape {
+            _ if self.range != 1 || self.centre => None,
+            Shape::Moore => Some(""),
+            Shape::VonNeumann => Some("V"),
+            Shape::Hexagonal => Some("H"),
+            Shape::Weighted => None,
+        }
+    }
+
+    /** parse the part of golly's `R..,C..,M..,S..,B..,N..` notation after the `N` */
+    pub(crate) fn parse(code: &str, range: u32) -> Result<Neighbourhood, RuleError> {
+        let invalid = || RuleError::InvalidNeighbourhood(code.to_string());
+        let mut chars = code.chars();
+        let shape = match chars.next().map(|ch| ch.to_ascii_uppercase()) {
+            Some('M') => Shape::Moore,
+            Some('N') => Shape::VonNeumann,
+            Some('H') => Shape::Hexagonal,
+            Some('W') => Shape::Weighted,
+            _ => return Err(invalid()),
+        };
+        let digits = chars.as_str();
+        if shape != Shape::Weighted {
+            return if digits.is_empty() { Self::shaped(shape, range) } else { Err(invalid()) };
+        }
+
+        // one hex digit per weight, or two (as a signed byte) for masks with larger or negative weights
+        let side = 2 * range as usize + 1;
+        let width = match digits.len() {
+            len if len == side * side => 1,
+            len if len == 2 * side * side => 2,
+            _ => return Err(invalid()),
+        };
+        let weights = (0..digits.len())
+            .step_by(width)
+            .map(|start| digits.get(start..start + width).and_then(|hex| u8::from_str_radix(hex, 16).ok()))
+            .map(|weight| weight.map(|weight| weight as i8))
+            .collect::<Option<Vec<i8>>>()
+            .ok_or_else(invalid)?;
+        Self::weighted(&weights)
+    }
+}
+
+impl Default for Neighbourhood {
+    fn default() -> Self {
+        Neighbourhood::moore(1).unwrap()
+    }
+}
+
+impl fmt::Display for Neighbourhood {
+    /** the `N..` part of golly's `R..,C..,M..,S..,B..,N..` notation, without the `N` */
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.shape {
+            Shape::Moore => write!(f, "M"),
+            Shape::VonNeumann => write!(f, "N"),
+            Shape::Hexagonal => write!(f, "H"),
+            Shape::Weighted => {
+                write!(f, "W")?;
+                let small = self.weights.iter().all(|weight| (0..16).contains(weight));
+                for weight in &self.weights {
+                    match small {
+                        true => write!(f, "{:x}", weight)?,
+                        false => write!(f, "{:02x}", *weight as u8)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{neighbourhood::{Neighbourhood, Shape}, rules::RuleError};
+
+    #[test]
+    fn test_offsets() {
+        let hexagonal = Neighbourhood::hexagonal(1).unwrap();
+        assert_eq!(Neighbourhood::default().max_count(), 8);
+        assert_eq!(hexagonal.max_count(), 6);
+        assert_eq!(hexagonal.weight(-1, 1), 0);
+        assert_eq!(hexagonal.weight(1, -1), 0);
+        // every neightbour sees the cell as its neightbour too
+        for neighbourhood in [Neighbourhood::default(), hexagonal] {
+            for (row, col, weight) in neighbourhood.cells() {
+                assert_eq!(neighbourhood.weight(-row, -col), weight);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ranges() {
+        assert_eq!(Neighbourhood::von_neumann(1).unwrap().cells(), vec![(-1, 0, 1), (0, -1, 1), (0, 1, 1), (1, 0, 1)]);
+        assert_eq!(Neighbourhood::von_neumann(3).unwrap().max_count(), 24);
+        assert_eq!(Neighbourhood::moore(2).unwrap().max_count(), 24);
+        assert_eq!(Neighbourhood::moore(2).unwrap().including_centre().max_count(), 25);
+        assert_eq!(Neighbourhood::hexagonal(2).unwrap().max_count(), 18);
+        assert_eq!(Neighbourhood::moore(0), Err(RuleError::InvalidNeighbourhood("range 0 is not within 1 to 500".to_string())));
+        assert!(Neighbourhood::von_neumann(501).is_err());
+    }
+
+    #[test]
+    fn test_weighted() {
+        let weighted = Neighbourhood::weighted(&[1, 2, 1, 2, 0, 2, 1, 2, -3]).unwrap();
+        assert_eq!(weighted.shape(), Shape::Weighted);
+        assert_eq!(weighted.range(), 1);
+        assert_eq!((weighted.weight(-1, 0), weighted.weight(1, 1), weighted.weight(0, 0), weighted.weight(2, 0)), (2, -3, 0, 0));
+        assert_eq!(weighted.max_count(), 11);
+        assert_eq!(weighted.clone().including_centre(), weighted);
+        assert_eq!(weighted.suffix(), None);
+        assert!(matches!(Neighbourhood::weighted(&[1; 8]), Err(RuleError::InvalidNeighbourhood(_))));
+        assert!(Neighbourhood::weighted(&[1; 25]).is_ok());
+    }
+
+    #[test]
+    fn test_notation() {
+        for code in ["M", "N", "H", "W121202121", "W010203040506070809fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0"] {
+            let range = if code.len() > 10 { 2 } else { 1 };
+            let neighbourhood = Neighbourhood::parse(code, range).unwrap();
+            assert_eq!(neighbourhood.to_string(), code);
+        }
+        assert_eq!(Neighbourhood::parse("n", 2), Neighbourhood::von_neumann(2));
+        assert_eq!(Neighbourhood::parse("W1212f2121", 1).unwrap().weight(0, 0), 15);
+        assert_eq!(Neighbourhood::parse("W1212ff2121", 1), Err(RuleError::InvalidNeighbourhood("W1212ff2121".to_string())));
+        assert_eq!(Neighbourhood::parse("X", 1), Err(RuleError::InvalidNeighbourhood("X".to_string())));
+        assert_eq!(Neighbourhood::parse("M2", 1), Err(RuleError::InvalidNeighbourhood("M2".to_string())));
+    }
+}
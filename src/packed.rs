@@ -0,0 +1,285 @@
+use std::borrow::Cow;
+
+use crate::{cells::Cell, rules::Rule, topology::Topology};
+
+const WORD_BITS: usize = 64;
+
+/**
+ * a bit-packed grid storing 64 cells per `u64`
+ *
+ * each row occupies `words_per_row` words, column `c` of a row lives in bit `c % 64`
+ * of word `c / 64`. the unused high bits of the last word in a row are always zero
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackedGrid {
+    width: u32,
+    height: u32,
+    words_per_row: usize,
+    words: Vec<u64>,
+}
+
+/** a row as seen from a neightbouring row, including the cells just beyond its ends */
+struct RowView<'a> {
+    words: Cow<'a, [u64]>,
+    west: u64,
+    east: u64,
+}
+
+impl PackedGrid {
+    pub fn new(width: u32, height: u32) -> PackedGrid {
+        let words_per_row = (width as usize).div_ceil(WORD_BITS);
+        PackedGrid {
+            width, height, words_per_row,
+            words: vec![0; words_per_row * height as usize],
+        }
+    }
+
+    pub fn from_cells(cells: &[Cell], width: u32, height: u32) -> PackedGrid {
+        let mut grid = PackedGrid::new(width, height);
+        for (row, chunk) in cells.chunks(width as usize).enumerate() {
+            let words = grid.row_mut(row as u32);
+            for (col, cell) in chunk.iter().enumerate() {
+                words[col / WORD_BITS] |= (cell.is_alive() as u64) << (col % WORD_BITS);
+            }
+        }
+        grid
+    }
+
+    /** unpack into a flattened one byte per cell layout as used by `Universe` */
+    pub fn write_cells(&self, cells: &mut [Cell]) {
+        for (row, chunk) in cells.chunks_mut(self.width as usize).enumerate() {
+            let words = self.row(row as u32);
+            for (col, cell) in chunk.iter_mut().enumerate() {
+                *cell = if words[col / WORD_BITS] >> (col % WORD_BITS) & 1 == 1 { Cell::Alive } else { Cell::Dead };
+            }
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn get(&self, row: u32, col: u32) -> bool {
+        let col = col as usize;
+        self.row(row)[col / WORD_BITS] >> (col % WORD_BITS) & 1 == 1
+    }
+
+    pub fn set(&mut self, row: u32, col: u32, alive: bool) {
+        let col = col as usize;
+        let word = &mut self.row_mut(row)[col / WORD_BITS];
+        if alive {
+            *word |= 1 << (col % WORD_BITS);
+        } else {
+            *word &= !(1 << (col % WORD_BITS));
+        }
+    }
+
+    pub fn population(&self) -> u64 {
+        self.words.iter().map(|word| word.count_ones() as u64).sum()
+    }
+
+    /** the row-major indices of the cells which differ from another grid of the same size, a word at a time */
+    pub fn changed<'a>(&'a self, other: &'a PackedGrid) -> impl Iterator<Item = usize> + 'a {
+        (0..self.height).flat_map(move |row| {
+            let words = self.row(row).iter().zip(other.row(row)).map(|(word, other)| word ^ other);
+            words.enumerate().flat_map(move |(index, mut differing)| {
+                std::iter::from_fn(move || {
+                    if differing == 0 {
+                        return None;
+                    }
+                    let bit = differing.trailing_zeros() as usize;
+                    differing &= differing - 1;
+                    Some(row as usize * self.width as usize + index * WORD_BITS + bit)
+                })
+            })
+        })
+    }
+
+    /**
+     * compute the next generation a whole word at a time
+     *
+     * the eight neightbours of every cell in a word are lined up as eight shifted words
+     * which are summed with a bitwise adder into four bit planes of the neightbour count
+     */
+    pub fn step(&self, rule: &Rule, topology: Topology) -> PackedGrid {
+        let mut next = PackedGrid::new(self.width, self.height);
+        if self.width == 0 {
+            return next;
+        }
+
+        let last_mask = match self.width as usize % WORD_BITS {
+            0 => u64::MAX,
+            used => (1 << used) - 1,
+        };
+        for row in 0..self.height {
+            let above = self.view(row as i64 - 1, topology);
+            let centre = self.view(row as i64, topology);
+            let below = self.view(row as i64 + 1, topology);
+
+            let next_words = next.row_mut(row);
+            for (index, next_word) in next_words.iter_mut().enumerate() {
+                let (nw, n, ne) = self.shifted(&above, index);
+                let (w, alive, e) = self.shifted(&centre, index);
+                let (sw, s, se) = self.shifted(&below, index);
+                *next_word = evolve_word(rule, alive, [nw, n, ne, w, e, sw, s, se]);
+            }
+            next_words[self.words_per_row - 1] &= last_mask;
+        }
+        next
+    }
+
+    fn row(&self, row: u32) -> &[u64] {
+        let start = row as usize * self.words_per_row;
+        &self.words[start..start + self.words_per_row]
+    }
+
+    fn row_mut(&mut self, row: u32) -> &mut [u64] {
+        let start = row as usize * self.words_per_row;
+        &mut self.words[start..start + self.words_per_row]
+    }
+
+    /** resolve a (possibly out of range) row through the topology */
+    fn view(&self, row: i64, topology: Topology) -> RowView<'_> {
+        let cell = |col: i64| {
+            topology.resolve(row, col, self.width, self.height)
+                .map_or(0, |(r, c)| self.get(r, c) as u64)
+        };
+        let words = match topology.resolve_row(row, self.height) {
+            Some((row, false)) => Cow::Borrowed(self.row(row)),
+            Some((row, true)) => {
+                let mut reversed = vec![0; self.words_per_row];
+                for col in 0..self.width {
+                    let mirrored = (self.width - 1 - col) as usize;
+                    reversed[mirrored / WORD_BITS] |= (self.get(row, col) as u64) << (mirrored % WORD_BITS);
+                }
+                Cow::Owned(reversed)
+            }
+            None => Cow::Owned(vec![0; self.words_per_row]),
+        };
+        RowView { words, west: cell(-1), east: cell(self.width as i64) }
+    }
+
+    /** the word at `index` together with the same word shifted to line up its west and east neightbours */
+    fn shifted(&self, view: &RowView, index: usize) -> (u64, u64, u64) {
+        let words = &view.words;
+        let word = words[index];
+        let carry_in_west = if index == 0 { view.west } else { words[index - 1] >> 63 };
+        let west = word << 1 | carry_in_west;
+        let east = if index + 1 < self.words_per_row {
+            word >> 1 | words[index + 1] << 63
+        } else {
+            let last_bit = (self.width as usize - 1) % WORD_BITS;
+            word >> 1 | view.east << last_bit
+        };
+        (west, word, east)
+    }
+}
+
+fn full_add(a: u64, b: u64, c: u64) -> (u64, u64) {
+    let partial = a ^ b;
+    (partial ^ c, a & b | partial & c)
+}
+
+fn half_add(a: u64, b: u64) -> (u64, u64) {
+    (a ^ b, a & b)
+}
+
+/** apply the rule to 64 cells at once given the eight words of their neightbours */
+pub(crate) fn evolve_word(rule: &Rule, alive: u64, neightbours: [u64; 8]) -> u64 {
+    let [nw, n, ne, w, e, sw, s, se] = neightbours;
+
+    // sum the eight one bit inputs into the bit planes `ones`, `twos`, `fours` and `eights`
+    let (sum_a, carry_a) = full_add(nw, n, ne);
+    let (sum_b, carry_b) = full_add(w, e, sw);
+    let (sum_c, carry_c) = half_add(s, se);
+    let (ones, carry_d) = full_add(sum_a, sum_b, sum_c);
+    let (twos_partial, fours_a) = full_add(carry_a, carry_b, carry_c);
+    let (twos, fours_b) = half_add(twos_partial, carry_d);
+    let (fours, eights) = half_add(fours_a, fours_b);
+
+    let mut next = 0;
+    for count in 0..=8u8 {
+        let born = rule.is_birth(count.into());
+        let survive = rule.is_survival(count.into());
+        if !born && !survive {
+            continue;
+        }
+        let plane = |bit: u8, plane: u64| if count >> bit & 1 == 1 { plane } else { !plane };
+        let matches = plane(0, ones) & plane(1, twos) & plane(2, fours) & plane(3, eights);
+        if born { next |= matches & !alive; }
+        if survive { next |= matches & alive; }
+    }
+    next
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{cells::Cell, packed::PackedGrid, random::soup, rules::Rule, topology::Topology, universe::{Backend, Universe}};
+
+    #[test]
+    fn test_round_trip() {
+        let mut cells = vec![Cell::Dead; 70 * 3];
+        for index in [0, 70 + 63, 70 + 64, 2 * 70 + 69] {
+            cells[index] = Cell::Alive;
+        }
+        let packed = PackedGrid::from_cells(&cells, 70, 3);
+        assert_eq!(packed.population(), 4);
+        assert!(packed.get(1, 64));
+        assert!(!packed.get(1, 65));
+
+        let mut unpacked = vec![Cell::Dead; 70 * 3];
+        packed.write_cells(&mut unpacked);
+        assert_eq!(unpacked, cells);
+    }
+
+    #[test]
+    fn test_set() {
+        let mut packed = PackedGrid::new(100, 2);
+        packed.set(1, 99, true);
+        assert!(packed.get(1, 99));
+        packed.set(1, 99, false);
+        assert_eq!(packed.population(), 0);
+    }
+
+    #[test]
+    fn test_changed() {
+        let mut before = PackedGrid::new(70, 2);
+        before.set(0, 3, true);
+        before.set(1, 65, true);
+        let mut after = before.clone();
+        after.set(0, 3, false);
+        after.set(0, 64, true);
+        after.set(1, 0, true);
+        assert_eq!(before.changed(&after).collect::<Vec<usize>>(), vec![3, 64, 70]);
+        assert_eq!(after.changed(&after).count(), 0);
+    }
+
+    #[test]
+    fn test_matches_dense() {
+        let topologies = [Topology::Torus, Topology::Bounded, Topology::Mirror, Topology::KleinBottle, Topology::CrossSurface];
+        let rules = ["B3/S23", "B36/S23", "B3678/S34678", "B2/S", "B0/S8"];
+        // widths on both sides of word boundaries
+        for (width, height) in [(5, 7), (64, 3), (65, 9), (130, 4)] {
+            for topology in topologies {
+                for rule in rules {
+                    let rule: Rule = rule.parse().unwrap();
+                    let mut universe = Universe::with_topology(width, height, topology);
+                    universe.set_rule(&rule.to_string()).unwrap();
+                    universe.init_cells(soup(7, width, height, 1.0 / 3.0));
+
+                    let mut packed = universe.clone();
+                    packed.set_backend(Backend::Packed);
+                    for _ in 0..8 {
+                        universe.next_epoch();
+                        packed.next_epoch();
+                        assert_eq!(universe.cells_to_arr(), packed.cells_to_arr(), "{}x{} {:?} {}", width, height, topology, rule);
+                    }
+                }
+            }
+        }
+    }
+}
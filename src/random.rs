@@ -0,0 +1,186 @@
+use std::{error::Error, fmt};
+
+use wasm_bindgen::prelude::*;
+
+/**
+ * a small splitmix64 generator, so the same seed gives the same soup
+ * on every platform without pulling in a dependency
+ */
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /** a uniformly distributed float in `[0, 1)` */
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /** `true` with the given probability */
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+}
+
+/**
+ * the `[row, col]` of the living cells of a `width` x `height` soup in row-major order,
+ * each cell being alive with probability `density`. for seeding tests and benchmarks without a universe
+ */
+pub fn soup(seed: u64, width: u32, height: u32, density: f64) -> Vec<[u32; 2]> {
+    let mut rng = Rng::new(seed);
+    (0..height)
+        .flat_map(|row| (0..width).map(move |col| [row, col]))
+        .filter(|_| rng.chance(density))
+        .collect()
+}
+
+/** the symmetry group a soup is made invariant under */
+#[wasm_bindgen]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Symmetry {
+    /** no symmetry at all */
+    #[default]
+    C1 = 0,
+    /** unchanged by a half turn */
+    C2 = 1,
+    /** unchanged by a quarter turn, square universes only */
+    C4 = 2,
+    /** mirrored left to right */
+    D2 = 3,
+    /** mirrored left to right and top to bottom */
+    D4 = 4,
+    /** unchanged by every rotation and reflection of the square, square universes only */
+    D8 = 5,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SoupError {
+    /** the density has to be a probability between 0 and 1 */
+    InvalidDensity(f64),
+    /** the region does not fit into the universe */
+    OutOfBounds { top: u32, left: u32, width: u32, height: u32 },
+    /** the symmetry maps rows onto columns, which needs a square universe */
+    NotSquare(Symmetry),
+}
+
+impl Symmetry {
+    pub fn check(self, width: u32, height: u32) -> Result<(), SoupError> {
+        match self {
+            Symmetry::C4 | Symmetry::D8 if width != height => Err(SoupError::NotSquare(self)),
+            _ => Ok(()),
+        }
+    }
+
+    /**
+     * every cell the symmetry maps `row`, `col` onto (including itself, possibly repeated)
+     * in a `width` x `height` grid which passed `check`
+     */
+    pub fn orbit(self, row: u32, col: u32, width: u32, height: u32) -> Vec<(u32, u32)> {
+        let (last_row, last_col) = (height - 1, width - 1);
+        let half_turn = (last_row - row, last_col - col);
+        let mirror = (row, last_col - col);
+        let flip = (last_row - row, col);
+        // only reached for square grids, so rows and columns can be swapped
+        let quarter_turns = [(col, last_row - row), (last_col - col, row)];
+        let diagonals = [(col, row), (last_col - col, last_row - row)];
+
+        let mut orbit = vec![(row, col)];
+        match self {
+            Symmetry::C1 => {}
+            Symmetry::C2 => orbit.push(half_turn),
+            Symmetry::C4 => orbit.extend([half_turn, quarter_turns[0], quarter_turns[1]]),
+            Symmetry::D2 => orbit.push(mirror),
+            Symmetry::D4 => orbit.extend([mirror, flip, half_turn]),
+            Symmetry::D8 => {
+                orbit.extend([mirror, flip, half_turn]);
+                orbit.extend(quarter_turns);
+                orbit.extend(diagonals);
+            }
+        }
+        orbit
+    }
+}
+
+pub(crate) fn check_density(density: f64) -> Result<(), SoupError> {
+    if (0.0..=1.0).contains(&density) {
+        Ok(())
+    } else {
+        Err(SoupError::InvalidDensity(density))
+    }
+}
+
+impl fmt::Display for SoupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SoupError::InvalidDensity(density) => write!(f, "density {} is not between 0 and 1", density),
+            SoupError::OutOfBounds { top, left, width, height } => write!(
+                f, "a {}x{} region at ({}, {}) does not fit into the universe", width, height, top, left
+            ),
+            SoupError::NotSquare(symmetry) => write!(f, "{:?} symmetry needs a square universe", symmetry),
+        }
+    }
+}
+
+impl Error for SoupError {}
+
+impl From<SoupError> for JsValue {
+    fn from(err: SoupError) -> Self {
+        js_sys::Error::new(&err.to_string()).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::random::{Rng, SoupError, Symmetry};
+
+    #[test]
+    fn test_reference_values() {
+        // the first outputs of the splitmix64 reference implementation
+        let mut rng = Rng::new(1234567);
+        assert_eq!(rng.next_u64(), 6457827717110365317);
+        assert_eq!(rng.next_u64(), 3203168211198807973);
+        assert_eq!(rng.next_u64(), 9817491932198370423);
+    }
+
+    #[test]
+    fn test_next_f64() {
+        let mut rng = Rng::new(42);
+        for _ in 0..1000 {
+            let value = rng.next_f64();
+            assert!((0.0..1.0).contains(&value));
+        }
+        assert!(!Rng::new(1).chance(0.0));
+        assert!(Rng::new(1).chance(1.0));
+    }
+
+    #[test]
+    fn test_orbit() {
+        let mut orbit = Symmetry::D8.orbit(0, 1, 4, 4);
+        orbit.sort_unstable();
+        orbit.dedup();
+        assert_eq!(orbit, vec![(0, 1), (0, 2), (1, 0), (1, 3), (2, 0), (2, 3), (3, 1), (3, 2)]);
+        assert_eq!(Symmetry::C2.orbit(0, 1, 5, 2), vec![(0, 1), (1, 3)]);
+        assert_eq!(Symmetry::D2.orbit(1, 0, 3, 2), vec![(1, 0), (1, 2)]);
+    }
+
+    #[test]
+    fn test_check() {
+        assert_eq!(Symmetry::C4.check(4, 5), Err(SoupError::NotSquare(Symmetry::C4)));
+        assert_eq!(Symmetry::D4.check(4, 5), Ok(()));
+        assert_eq!(Symmetry::D8.check(6, 6), Ok(()));
+    }
+}
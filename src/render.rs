@@ -0,0 +1,461 @@
+use std::{error::Error, fmt};
+
+use wasm_bindgen::prelude::*;
+
+use crate::{cells::Cell, hexagonal::HexLayout, neighbourhood::Shape, universe::Universe};
+
+const BYTES_PER_PIXEL: usize = 4;
+
+/** the most pixels a frame may have, a gigabyte of RGBA */
+pub const MAX_FRAME_PIXELS: u64 = 1 << 28;
+
+/** the size of a frame which would have more than `MAX_FRAME_PIXELS` pixels */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameError {
+    pub width: u64,
+    pub height: u64,
+}
+
+/** the colours of a rendered frame as `[r, g, b, a]` */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Palette {
+    pub alive: [u8; 4],
+    pub dead: [u8; 4],
+    pub grid: [u8; 4],
+}
+
+impl Default for Palette {
+    /** black cells on white with light grey grid lines, like the original canvas renderer */
+    fn default() -> Palette {
+        Palette {
+            alive: [0x00, 0x00, 0x00, 0xff],
+            dead: [0xff, 0xff, 0xff, 0xff],
+            grid: [0xcc, 0xcc, 0xcc, 0xff],
+        }
+    }
+}
+
+impl Palette {
+    /** the colour of a cell under a rule with `states` states, dying cells fade from alive to dead */
+    pub fn color(&self, cell: Cell, states: u16) -> [u8; 4] {
+        match cell {
+            Cell::Alive => self.alive,
+            Cell::Dead => self.dead,
+            dying => {
+                let (step, steps) = (dying.state() as u32 - 1, states.max(dying.state() as u16 + 1) as u32 - 1);
+                let mut color = [0; 4];
+                for ((channel, from), to) in color.iter_mut().zip(self.alive).zip(self.dead) {
+                    *channel = ((from as u32 * (steps - step) + to as u32 * step + steps / 2) / steps) as u8;
+                }
+                color
+            }
+        }
+    }
+}
+
+/**
+ * draws a universe into an RGBA framebuffer, one `cell_size` x `cell_size` square per cell.
+ * with grid lines every cell is surrounded by a 1 pixel border, so cell `row`, `col` starts at
+ * pixel `col * (cell_size + 1) + 1`, `row * (cell_size + 1) + 1`.
+ * universes with a hexagonal rule are drawn as hexagons `cell_size` pixels wide instead,
+ * at the offset coordinates of `Universe::cell_to_offset` and without grid lines.
+ * the pixels are laid out like `ImageData`, so js can hand `frame_ptr` to `putImageData`
+ */
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Renderer {
+    cell_size: u32,
+    grid_lines: bool,
+    palette: Palette,
+    /** colours replacing the palette's fade for the dying states, indexed by state */
+    state_colors: Vec<Option<[u8; 4]>>,
+    /** whether the last frame was drawn with hexagons */
+    hexagonal: bool,
+    frame_width: u32,
+    frame_height: u32,
+    frame: Vec<u8>,
+}
+
+impl Renderer {
+    pub fn palette(&self) -> Palette {
+        self.palette
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    pub fn frame(&self) -> &[u8] {
+        &self.frame
+    }
+
+    /** the colour a cell is drawn in under a rule with `states` states */
+    pub fn color(&self, cell: Cell, states: u16) -> [u8; 4] {
+        match self.state_colors.get(cell.state() as usize) {
+            Some(Some(color)) => *color,
+            _ => self.palette.color(cell, states),
+        }
+    }
+
+    /** the colour of the pixel at `x`, `y` in the last frame */
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let start = (y as usize * self.frame_width as usize + x as usize) * BYTES_PER_PIXEL;
+        let mut pixel = [0; 4];
+        pixel.copy_from_slice(&self.frame[start..start + BYTES_PER_PIXEL]);
+        pixel
+    }
+
+    fn pitch(&self) -> u64 {
+        self.cell_size as u64 + self.grid_lines as u64
+    }
+
+    /**
+     * resize the frame for a universe, returns whether it had to be redrawn from scratch.
+     * a frame too large to be allocated is dropped
+     */
+    fn fit(&mut self, universe: &Universe) -> Result<bool, FrameError> {
+        let hexagonal = universe.rule().neighbourhood().shape() == Shape::Hexagonal;
+        let border = self.grid_lines as u64;
+        let (frame_width, frame_height) = if hexagonal {
+            HexLayout::new(self.cell_size).frame_size(universe.hex_columns(), universe.height())
+        } else {
+            (universe.width() as u64 * self.pitch() + border, universe.height() as u64 * self.pitch() + border)
+        };
+        if frame_width.checked_mul(frame_height).is_none_or(|pixels| pixels > MAX_FRAME_PIXELS) {
+            self.frame_width = 0;
+            self.frame_height = 0;
+            self.frame = vec![];
+            return Err(FrameError { width: frame_width, height: frame_height });
+        }
+        // both fit into a u32 now
+        let (frame_width, frame_height) = (frame_width as u32, frame_height as u32);
+        let unchanged = (frame_width, frame_height, hexagonal) == (self.frame_width, self.frame_height, self.hexagonal);
+        if unchanged && !self.frame.is_empty() {
+            return Ok(false);
+        }
+
+        self.hexagonal = hexagonal;
+        self.frame_width = frame_width;
+        self.frame_height = frame_height;
+        let grid = self.palette.grid;
+        self.frame = grid.repeat(frame_width as usize * frame_height as usize);
+        Ok(true)
+    }
+
+    fn fill_cell(&mut self, universe: &Universe, index: usize) {
+        let width = universe.width() as usize;
+        let (row, col) = ((index / width) as u32, (index % width) as u32);
+        let color = self.color(universe.cell_slice()[index], universe.rule().states());
+        if self.hexagonal {
+            self.fill_hexagon(universe, row, col, color);
+            return;
+        }
+
+        let border = self.grid_lines as u64;
+        let (left, top) = (col as u64 * self.pitch() + border, row as u64 * self.pitch() + border);
+        for y in top..top + self.cell_size as u64 {
+            let start = (y as usize * self.frame_width as usize + left as usize) * BYTES_PER_PIXEL;
+            let end = start + self.cell_size as usize * BYTES_PER_PIXEL;
+            for pixel in self.frame[start..end].chunks_exact_mut(BYTES_PER_PIXEL) {
+                pixel.copy_from_slice(&color);
+            }
+        }
+    }
+
+    fn fill_hexagon(&mut self, universe: &Universe, row: u32, col: u32, color: [u8; 4]) {
+        let (row, col) = universe.hex_offset(row, col);
+        let layout = HexLayout::new(self.cell_size);
+        let (xs, ys) = layout.bounds(row, col);
+        for y in ys.start.max(0)..ys.end.min(self.frame_height as i64) {
+            for x in xs.start.max(0)..xs.end.min(self.frame_width as i64) {
+                if layout.contains(row, col, x, y) {
+                    let start = (y as usize * self.frame_width as usize + x as usize) * BYTES_PER_PIXEL;
+                    self.frame[start..start + BYTES_PER_PIXEL].copy_from_slice(&color);
+                }
+            }
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Renderer {
+    pub fn new(cell_size: u32, grid_lines: bool) -> Renderer {
+        Renderer {
+            cell_size: cell_size.max(1),
+            grid_lines,
+            palette: Palette::default(),
+            state_colors: vec![],
+            hexagonal: false,
+            frame_width: 0,
+            frame_height: 0,
+            frame: vec![],
+        }
+    }
+
+    pub fn cell_size(&self) -> u32 {
+        self.cell_size
+    }
+
+    pub fn set_cell_size(&mut self, cell_size: u32) {
+        self.cell_size = cell_size.max(1);
+        self.frame.clear();
+    }
+
+    pub fn grid_lines(&self) -> bool {
+        self.grid_lines
+    }
+
+    pub fn set_grid_lines(&mut self, grid_lines: bool) {
+        self.grid_lines = grid_lines;
+        self.frame.clear();
+    }
+
+    /** colours are given as `0xRRGGBBAA` */
+    pub fn set_alive_color(&mut self, rgba: u32) {
+        self.palette.alive = rgba.to_be_bytes();
+        self.frame.clear();
+    }
+
+    pub fn set_dead_color(&mut self, rgba: u32) {
+        self.palette.dead = rgba.to_be_bytes();
+        self.frame.clear();
+    }
+
+    pub fn set_grid_color(&mut self, rgba: u32) {
+        self.palette.grid = rgba.to_be_bytes();
+        self.frame.clear();
+    }
+
+    /** the colour of any state, 0 and 1 are the dead and alive colours */
+    pub fn set_state_color(&mut self, state: u8, rgba: u32) {
+        match Cell::new(state) {
+            Cell::Dead => self.palette.dead = rgba.to_be_bytes(),
+            Cell::Alive => self.palette.alive = rgba.to_be_bytes(),
+            _ => {
+                let index = state as usize;
+                if self.state_colors.len() <= index {
+                    self.state_colors.resize(index + 1, None);
+                }
+                self.state_colors[index] = Some(rgba.to_be_bytes());
+            }
+        }
+        self.frame.clear();
+    }
+
+    /** go back to fading the dying states from the alive to the dead colour */
+    pub fn clear_state_colors(&mut self) {
+        self.state_colors.clear();
+        self.frame.clear();
+    }
+
+    /** draw every cell of the universe, unless the frame would have more than `MAX_FRAME_PIXELS` pixels */
+    pub fn render(&mut self, universe: &Universe) -> Result<(), FrameError> {
+        self.fit(universe)?;
+        for index in 0..universe.cell_slice().len() {
+            self.fill_cell(universe, index);
+        }
+        Ok(())
+    }
+
+    /**
+     * only redraw the cells born or died in the last change of the universe,
+     * which is enough if the previous frame showed the universe right before that change
+     */
+    pub fn render_changes(&mut self, universe: &Universe) -> Result<(), FrameError> {
+        if self.fit(universe)? {
+            return self.render(universe);
+        }
+        let (born, died) = universe.changes();
+        for index in born.iter().chain(died) {
+            self.fill_cell(universe, *index as usize);
+        }
+        Ok(())
+    }
+
+    pub fn frame_width(&self) -> u32 {
+        self.frame_width
+    }
+
+    pub fn frame_height(&self) -> u32 {
+        self.frame_height
+    }
+
+    /** the RGBA pixels of the last frame, `frame_len` bytes from wasm memory */
+    pub fn frame_ptr(&self) -> *const u8 {
+        self.frame.as_ptr()
+    }
+
+    pub fn frame_len(&self) -> usize {
+        self.frame.len()
+    }
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a {}x{} pixel frame is larger than {} pixels", self.width, self.height, MAX_FRAME_PIXELS)
+    }
+}
+
+impl Error for FrameError {}
+
+impl From<FrameError> for JsValue {
+    fn from(err: FrameError) -> Self {
+        js_sys::Error::new(&err.to_string()).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{cells::Cell, hexagonal::HexLayout, render::{FrameError, Palette, Renderer}, topology::Topology, universe::Universe};
+
+    #[test]
+    fn test_frame_too_large() {
+        let universe = Universe::new(3, 2);
+        let mut renderer = Renderer::new(u32::MAX, true);
+        let pitch = u32::MAX as u64 + 1;
+        assert_eq!(renderer.render(&universe), Err(FrameError { width: 3 * pitch + 1, height: 2 * pitch + 1 }));
+        assert_eq!((renderer.frame_width(), renderer.frame_height(), renderer.frame_len()), (0, 0, 0));
+        assert!(renderer.render_changes(&universe).is_err());
+
+        // the last frame that fitted is dropped
+        let mut renderer = Renderer::new(4, false);
+        renderer.render(&universe).unwrap();
+        renderer.set_cell_size(1 << 14);
+        assert!(renderer.render(&universe).is_err());
+        assert_eq!(renderer.frame_len(), 0);
+    }
+
+    #[test]
+    fn test_render_with_grid() {
+        let mut universe = Universe::new(2, 1);
+        universe.init_cells(vec![[0, 1]]);
+        let mut renderer = Renderer::new(2, true);
+        renderer.render(&universe).unwrap();
+
+        let palette = Palette::default();
+        assert_eq!((renderer.frame_width(), renderer.frame_height()), (7, 4));
+        assert_eq!(renderer.frame_len(), 7 * 4 * 4);
+        let expected_rows = [
+            [palette.grid; 7],
+            [palette.grid, palette.dead, palette.dead, palette.grid, palette.alive, palette.alive, palette.grid],
+            [palette.grid, palette.dead, palette.dead, palette.grid, palette.alive, palette.alive, palette.grid],
+            [palette.grid; 7],
+        ];
+        for (y, row) in expected_rows.iter().enumerate() {
+            for (x, pixel) in row.iter().enumerate() {
+                assert_eq!(renderer.pixel(x as u32, y as u32), *pixel, "pixel {}, {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_render_without_grid() {
+        let mut universe = Universe::new(2, 2);
+        universe.init_cells(vec![[1, 0]]);
+        let mut renderer = Renderer::new(1, false);
+        renderer.set_alive_color(0xff00_00ff);
+        renderer.set_dead_color(0x0000_0000);
+        renderer.render(&universe).unwrap();
+        assert_eq!(renderer.frame(), [0, 0, 0, 0, 0, 0, 0, 0, 255, 0, 0, 255, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_render_changes() {
+        let mut universe = Universe::new(16, 16);
+        universe.randomize(5, 0.4).unwrap();
+        let mut incremental = Renderer::new(3, true);
+        incremental.render(&universe).unwrap();
+
+        for _ in 0..10 {
+            universe.tick();
+            incremental.render_changes(&universe).unwrap();
+        }
+        universe.toggle_cell(4, 4).unwrap();
+        incremental.render_changes(&universe).unwrap();
+
+        let mut full = Renderer::new(3, true);
+        full.render(&universe).unwrap();
+        assert_eq!(incremental.frame(), full.frame());
+    }
+
+    #[test]
+    fn test_state_colors() {
+        let palette = Palette::default();
+        assert_eq!(palette.color(Cell::new(2), 3), [0x80, 0x80, 0x80, 0xff]);
+        assert_eq!(palette.color(Cell::new(2), 5), [0x40, 0x40, 0x40, 0xff]);
+        assert_eq!(palette.color(Cell::new(4), 5), [0xbf, 0xbf, 0xbf, 0xff]);
+
+        let mut universe = Universe::new(3, 1);
+        universe.set_rule("B2/S/C4").unwrap();
+        universe.set_cell(0, 1, Cell::new(2)).unwrap();
+        universe.set_cell(0, 2, Cell::new(3)).unwrap();
+        let mut renderer = Renderer::new(1, false);
+        renderer.set_state_color(3, 0x00ff_00ff);
+        renderer.set_state_color(0, 0x0000_00ff);
+        renderer.render(&universe).unwrap();
+        assert_eq!(renderer.pixel(0, 0), [0x00, 0x00, 0x00, 0xff]);
+        assert_eq!(renderer.pixel(1, 0), renderer.palette().color(Cell::new(2), 4));
+        assert_eq!(renderer.pixel(2, 0), [0x00, 0xff, 0x00, 0xff]);
+
+        renderer.clear_state_colors();
+        renderer.render(&universe).unwrap();
+        assert_eq!(renderer.pixel(2, 0), renderer.palette().color(Cell::new(3), 4));
+    }
+
+    #[test]
+    fn test_render_bounded_hexagons() {
+        // every cell is drawn where `cell_to_offset` says, even the ones a torus would wrap around
+        let mut universe = Universe::with_topology(4, 5, Topology::Bounded);
+        universe.set_rule("B2/S34H").unwrap();
+        let mut renderer = Renderer::new(8, false);
+        let layout = HexLayout::new(8);
+        for row in 0..5 {
+            for col in 0..4 {
+                universe.clear();
+                universe.set_cell(row, col, Cell::Alive).unwrap();
+                renderer.render(&universe).unwrap();
+                assert_eq!((renderer.frame_width() as u64, renderer.frame_height() as u64), layout.frame_size(6, 5));
+
+                let offset = universe.cell_to_offset(row, col).unwrap();
+                let (offset_row, offset_col) = (offset[0] as i64, offset[1] as i64);
+                let (xs, ys) = layout.bounds(offset_row, offset_col);
+                let inside = ys.flat_map(|y| xs.clone().map(move |x| (x, y)))
+                    .find(|(x, y)| layout.contains(offset_row, offset_col, *x, *y))
+                    .unwrap();
+                assert_eq!(renderer.pixel(inside.0 as u32, inside.1 as u32), Palette::default().alive, "{}, {}", row, col);
+            }
+        }
+    }
+
+    #[test]
+    fn test_render_hexagons() {
+        let mut universe = Universe::new(4, 3);
+        universe.set_rule("B2/S34H").unwrap();
+        universe.init_cells(vec![[1, 1]]);
+        let mut renderer = Renderer::new(8, true);
+        renderer.render(&universe).unwrap();
+        assert_eq!((renderer.frame_width(), renderer.frame_height()), (36, 24));
+
+        // the hexagon is drawn at offset 1, 1, in an odd row which is not pushed to the right
+        let palette = Palette::default();
+        assert_eq!(renderer.pixel(12, 12), palette.alive);
+        assert_eq!(renderer.pixel(12, 8), palette.alive);
+        assert_eq!(renderer.pixel(8, 8), palette.dead);
+        assert_eq!(renderer.pixel(3, 12), palette.dead);
+        assert_eq!(renderer.pixel(35, 12), palette.grid);
+        let alive = renderer.frame().chunks(4).filter(|pixel| *pixel == palette.alive).count();
+        assert!((50..=64).contains(&alive), "{} pixels", alive);
+
+        let mut incremental = renderer.clone();
+        for _ in 0..4 {
+            universe.tick();
+            incremental.render_changes(&universe).unwrap();
+        }
+        renderer.render(&universe).unwrap();
+        assert_eq!(incremental.frame(), renderer.frame());
+
+        universe.set_rule("B3/S23").unwrap();
+        renderer.render(&universe).unwrap();
+        assert_eq!((renderer.frame_width(), renderer.frame_height()), (37, 28));
+    }
+}
@@ -0,0 +1,597 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use wasm_bindgen::prelude::*;
+
+use crate::{cells::Cell, neighbourhood::{Neighbourhood, Shape}, wireworld};
+
+/** the most states a cell can have, as they are stored in a byte */
+pub const MAX_STATES: u16 = 256;
+
+/**
+ * an outer-totalistic rule in the B/S notation (e.g. `B3/S23` for conway's game of life)
+ *
+ * `birth[n]` tells whether a dead cell with `n` living neightbours becomes alive,
+ * `survival[n]` tells whether a living cell with `n` living neightbours stays alive.
+ * "generations" rules like `B2/S/C3` (brian's brain) have more than two `states`:
+ * a living cell which does not survive goes through the dying states 2, 3, .. `states - 1`
+ * one generation at a time before it is dead, and can't be born again until then.
+ * a `H` suffix (`B2/S34H`) counts the neightbours on a hexagonal lattice instead, a `V` suffix
+ * only counts the 4 von neumann neightbours. larger and weighted neighbourhoods are written the way
+ * golly does, e.g. `R2,C0,M0,S2..3,B3,NN` (see `Neighbourhood`), and `n` is then their weighted sum.
+ * `WireWorld` is not outer-totalistic at all, see `wireworld::next_state`
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    birth: Vec<bool>,
+    survival: Vec<bool>,
+    states: u16,
+    neighbourhood: Neighbourhood,
+    family: Family,
+}
+
+/** how the next state of a cell follows from its state and its living neightbours */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Family {
+    /** life-like and generations rules, given by `birth`, `survival` and `states` */
+    Totalistic,
+    /** the living neightbours are the electron heads */
+    Wireworld,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RuleError {
+    /** the rulestring is empty */
+    Empty,
+    /** a neightbour count outside of 0..=8 */
+    InvalidDigit(char),
+    /** a neightbour count larger than the neighbourhood can reach */
+    InvalidCount(u32),
+    /** the rulestring does not follow either `B../S..`, `../..` or `R..,C..,M..,S..,B..,N..` */
+    Malformed(String),
+    /** a number of states outside of 2..=256 */
+    InvalidStates(String),
+    /** a neighbourhood with no valid range, shape or weights */
+    InvalidNeighbourhood(String),
+    /** a valid rule which cannot be run by a particular kind of universe */
+    Unsupported(String),
+}
+
+impl Rule {
+    pub fn new(birth: &[u8], survival: &[u8]) -> Result<Rule, RuleError> {
+        Ok(Rule {
+            birth: Self::counts(birth)?,
+            survival: Self::counts(survival)?,
+            states: 2,
+            neighbourhood: Neighbourhood::default(),
+            family: Family::Totalistic,
+        })
+    }
+
+    /** a rule in which cells take `states - 1` generations to die */
+    pub fn generations(birth: &[u8], survival: &[u8], states: u16) -> Result<Rule, RuleError> {
+        Ok(Rule {
+            states: Self::check_states(states)?,
+            ..Rule::new(birth, survival)?
+        })
+    }
+
+    /** B3/S23 */
+    pub fn conway() -> Rule {
+        Rule::new(&[3], &[2, 3]).unwrap()
+    }
+
+    /** brian silverman's wireworld, with the states described in `wireworld` */
+    pub fn wireworld() -> Rule {
+        Rule {
+            birth: vec![],
+            survival: vec![],
+            states: 4,
+            neighbourhood: Neighbourhood::default(),
+            family: Family::Wireworld,
+        }
+    }
+
+    pub fn is_wireworld(&self) -> bool {
+        self.family == Family::Wireworld
+    }
+
+    /** the same rule counting neightbours over another neighbourhood, which must be able to reach every count */
+    pub fn with_neighbourhood(self, neighbourhood: Neighbourhood) -> Result<Rule, RuleError> {
+        let largest = self.birth.len().max(self.survival.len()).saturating_sub(1) as u32;
+        if largest > neighbourhood.max_count() {
+            return Err(RuleError::InvalidCount(largest));
+        }
+        Ok(Rule { neighbourhood, ..self })
+    }
+
+    pub fn neighbourhood(&self) -> &Neighbourhood {
+        &self.neighbourhood
+    }
+
+    /**
+     * determine the state of a cell in the next epoch given its neightours states
+     *
+     * with B3/S23 this is the law of hades
+     * 1. Any live cell with fewer than two live neighbours dies, as if caused by underpopulation.
+     * 2. Any live cell with two or three live neighbours lives on to the next generation.
+     * 3. Any live cell with more than three live neighbours dies, as if by overpopulation.
+     * 4. Any dead cell with exactly three live neighbours becomes a live cell, as if by reproduction.
+     */
+    pub fn next_state(&self, cell: Cell, living_neightbours: i32) -> Cell {
+        if self.family == Family::Wireworld {
+            return wireworld::next_state(cell, living_neightbours);
+        }
+        match cell {
+            Cell::Dead if self.is_birth(living_neightbours) => Cell::Alive,
+            Cell::Dead => Cell::Dead,
+            Cell::Alive if self.is_survival(living_neightbours) => Cell::Alive,
+            // dying cells carry on dying whatever their neightbours
+            dying if (dying.state() as u16 + 1) < self.states => Cell::new(dying.state() + 1),
+            _ => Cell::Dead,
+        }
+    }
+
+    /** how many states a cell can be in, 2 unless this is a generations rule */
+    pub fn states(&self) -> u16 {
+        self.states
+    }
+
+    /** whether the cell is in one of the states of this rule */
+    pub fn has_state(&self, cell: Cell) -> bool {
+        (cell.state() as u16) < self.states
+    }
+
+    /** whether a dead cell comes to life, negative weighted sums never give birth */
+    pub fn is_birth(&self, living_neightbours: i32) -> bool {
+        Self::contains(&self.birth, living_neightbours)
+    }
+
+    pub fn is_survival(&self, living_neightbours: i32) -> bool {
+        Self::contains(&self.survival, living_neightbours)
+    }
+
+    fn contains(counts: &[bool], count: i32) -> bool {
+        count >= 0 && counts.get(count as usize).copied().unwrap_or(false)
+    }
+
+    /** check that the rule keeps an infinite dead background dead, as required by unbounded universes */
+    pub fn check_unbounded(&self) -> Result<(), RuleError> {
+        if self.is_birth(0) {
+            return Err(RuleError::Unsupported("B0 rules would fill an unbounded universe".to_string()));
+        }
+        Ok(())
+    }
+
+    /** whether the rule only has dead and living cells counting their moore neightbours */
+    pub fn is_life_like(&self) -> bool {
+        self.states == 2 && self.neighbourhood == Neighbourhood::default() && self.family == Family::Totalistic
+    }
+
+    /** check that the rule is life-like, as required by the bit packed universes and hashlife */
+    pub fn check_life_like(&self) -> Result<(), RuleError> {
+        if self.states > 2 {
+            return Err(RuleError::Unsupported(format!("{} states do not fit into one bit", self.states)));
+        }
+        if !self.is_life_like() {
+            return Err(RuleError::Unsupported(format!("{} is not a life-like rule", self)));
+        }
+        Ok(())
+    }
+
+    fn check_states(states: u16) -> Result<u16, RuleError> {
+        if !(2..=MAX_STATES).contains(&states) {
+            return Err(RuleError::InvalidStates(states.to_string()));
+        }
+        Ok(states)
+    }
+
+    /** the `C3` in `B2/S/C3`, also accepted as `G3` or a plain `3` */
+    fn parse_states(section: &str) -> Result<u16, RuleError> {
+        let digits = section.strip_prefix(['C', 'c', 'G', 'g']).unwrap_or(section);
+        let states = digits.parse().map_err(|_| RuleError::InvalidStates(digits.to_string()))?;
+        Self::check_states(states)
+    }
+
+    fn counts(digits: &[u8]) -> Result<Vec<bool>, RuleError> {
+        let mut counts = vec![false; 9];
+        for &digit in digits {
+            if digit > 8 {
+                return Err(RuleError::InvalidDigit(std::char::from_digit(digit.into(), 10).unwrap_or('?')));
+            }
+            counts[digit as usize] = true;
+        }
+        Ok(Self::trimmed(counts))
+    }
+
+    fn parse_digits(section: &str) -> Result<Vec<bool>, RuleError> {
+        let mut counts = vec![false; 9];
+        for ch in section.chars() {
+            match ch.to_digit(10) {
+                Some(digit) if digit <= 8 => counts[digit as usize] = true,
+                _ => return Err(RuleError::InvalidDigit(ch)),
+            }
+        }
+        Ok(Self::trimmed(counts))
+    }
+
+    /** drop the counts past the largest one, so equal rules compare equal */
+    fn trimmed(mut counts: Vec<bool>) -> Vec<bool> {
+        let len = counts.iter().rposition(|count| *count).map_or(0, |largest| largest + 1);
+        counts.truncate(len);
+        counts
+    }
+
+    /**
+     * parse golly's notation for rules over larger or weighted neighbourhoods, e.g. `R2,C0,M1,S2..3,5,B3,NN`:
+     * the range, the number of states (0 for 2), whether the cell counts itself, the survival and birth counts
+     * as single counts or `..` (or `-`) ranges, and the neighbourhood (`M` if left out)
+     */
+    fn parse_ranged(rulestring: &str) -> Result<Rule, RuleError> {
+        let malformed = || RuleError::Malformed(rulestring.to_string());
+        let (mut range, mut states, mut centre, mut code) = (None, 2, false, "M");
+        let (mut birth, mut survival) = (vec![], vec![]);
+        // the key of the last field, as counts after a `S` or `B` field carry on its list
+        let mut key = ' ';
+        for field in rulestring.split(',').map(str::trim) {
+            let value = match field.chars().next() {
+                Some(first) if first.is_ascii_alphabetic() => {
+                    key = first.to_ascii_uppercase();
+                    &field[1..]
+                }
+                _ => field,
+            };
+            match key {
+                'R' => range = Some(value.parse::<u32>().map_err(|_| malformed())?),
+                'C' => states = match value.parse::<u16>() {
+                    Ok(0) | Ok(1) => 2,
+                    _ => Self::parse_states(value)?,
+                },
+                'M' => centre = match value {
+                    "0" => false,
+                    "1" => true,
+                    _ => return Err(malformed()),
+                },
+                'S' | 'B' if value.is_empty() => {}
+                'S' => survival.push(Self::parse_range(value).ok_or_else(malformed)?),
+                'B' => birth.push(Self::parse_range(value).ok_or_else(malformed)?),
+                'N' => code = value,
+                _ => return Err(malformed()),
+            }
+            // only the count lists carry on past their first field
+            if !matches!(key, 'S' | 'B') {
+                key = ' ';
+            }
+        }
+
+        let mut neighbourhood = Neighbourhood::parse(code, range.ok_or_else(malformed)?)?;
+        if centre {
+            neighbourhood = neighbourhood.including_centre();
+        }
+        // check the counts before making room for them
+        let max_count = neighbourhood.max_count();
+        if let Some(largest) = birth.iter().chain(&survival).map(|(_, to)| *to).filter(|to| *to > max_count).max() {
+            return Err(RuleError::InvalidCount(largest));
+        }
+        let counts = |ranges: &[(u32, u32)]| {
+            let mut counts = vec![false; max_count as usize + 1];
+            for (from, to) in ranges {
+                counts[*from as usize..=*to as usize].fill(true);
+            }
+            Self::trimmed(counts)
+        };
+        Ok(Rule {
+            birth: counts(&birth),
+            survival: counts(&survival),
+            states,
+            neighbourhood,
+            family: Family::Totalistic,
+        })
+    }
+
+    /** a single count `3`, or the inclusive range `2..5` (also `2-5`) */
+    fn parse_range(range: &str) -> Option<(u32, u32)> {
+        let (from, to) = range.split_once("..").or_else(|| range.split_once('-')).unwrap_or((range, range));
+        let (from, to) = (from.trim().parse().ok()?, to.trim().parse().ok()?);
+        if from > to {
+            return None;
+        }
+        Some((from, to))
+    }
+
+    /** write the counts as golly does, `2..3,5` */
+    fn write_ranges(f: &mut fmt::Formatter<'_>, counts: &[bool]) -> fmt::Result {
+        let mut separator = "";
+        let mut count = 0;
+        while count < counts.len() {
+            if !counts[count] {
+                count += 1;
+                continue;
+            }
+            let end = count + counts[count..].iter().take_while(|included| **included).count() - 1;
+            match end > count {
+                true => write!(f, "{}{}..{}", separator, count, end)?,
+                false => write!(f, "{}{}", separator, count)?,
+            }
+            separator = ",";
+            count = end + 1;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Rule::conway()
+    }
+}
+
+impl FromStr for Rule {
+    type Err = RuleError;
+
+    /**
+     * parse a rulestring in either the `B36/S23` form (case insensitive, in any order,
+     * slash optional) or the legacy `S/B` form (e.g. `23/36`),
+     * optionally followed by the number of states of a generations rule (`B2/S/C3` or `/2/3`),
+     * or `WireWorld`. a trailing `H` selects the hexagonal neighbourhood, a trailing `V` the von neumann one.
+     * rulestrings starting with a range, like `R2,C0,M0,S2..3,B3,NN`, follow `parse_ranged`
+     */
+    fn from_str(rulestring: &str) -> Result<Self, Self::Err> {
+        let rulestring = rulestring.trim();
+        if rulestring.is_empty() {
+            return Err(RuleError::Empty);
+        }
+        if rulestring.eq_ignore_ascii_case("wireworld") {
+            return Ok(Rule::wireworld());
+        }
+        if rulestring.starts_with(['R', 'r']) {
+            return Self::parse_ranged(rulestring);
+        }
+
+        let (body, neighbourhood) = match rulestring.strip_suffix(['H', 'h']) {
+            Some(body) => (body, Neighbourhood::hexagonal(1)?),
+            None => match rulestring.strip_suffix(['V', 'v']) {
+                Some(body) => (body, Neighbourhood::von_neumann(1)?),
+                None => (rulestring, Neighbourhood::default()),
+            },
+        };
+        let mut sections: Vec<&str> = body.split('/').collect();
+        // also accept the slashless `B3S23` form
+        if sections.len() == 1 {
+            if let Some(split) = body.find(['S', 's']) {
+                sections = vec![&body[..split], &body[split..]];
+            }
+        }
+
+        let prefixed = |section: &str, prefix: char| {
+            section.chars().next().map(|ch| ch.to_ascii_uppercase()) == Some(prefix)
+        };
+        let states = match sections.len() {
+            2 => 2,
+            // a plain number of states only follows the legacy `S/B` form
+            3 if prefixed(sections[2], 'C') || prefixed(sections[2], 'G')
+                || !prefixed(sections[0], 'B') && !prefixed(sections[0], 'S') => Self::parse_states(sections.pop().unwrap())?,
+            _ => return Err(RuleError::Malformed(rulestring.to_string())),
+        };
+        let (birth, survival) = match (sections[0], sections[1]) {
+            (b, s) if prefixed(b, 'B') && prefixed(s, 'S') => (&b[1..], &s[1..]),
+            (s, b) if prefixed(s, 'S') && prefixed(b, 'B') => (&b[1..], &s[1..]),
+            (s, b) if !prefixed(s, 'B') && !prefixed(s, 'S') && !prefixed(b, 'B') && !prefixed(b, 'S') => (b, s),
+            _ => return Err(RuleError::Malformed(rulestring.to_string())),
+        };
+
+        let rule = Rule {
+            birth: Self::parse_digits(birth)?,
+            survival: Self::parse_digits(survival)?,
+            states,
+            neighbourhood: Neighbourhood::default(),
+            family: Family::Totalistic,
+        };
+        rule.with_neighbourhood(neighbourhood)
+    }
+}
+
+impl fmt::Display for Rule {
+    /**
+     * display the rule in the canonical `B../S..` (or `B../S../C..`) notation, or `R..,C..,M..,S..,B..,N..`
+     * when that can't describe the neighbourhood, the way golly names it otherwise
+     */
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.family == Family::Wireworld {
+            return write!(f, "WireWorld");
+        }
+        let suffix = match self.neighbourhood.suffix() {
+            Some(suffix) => suffix,
+            None => {
+                let states = if self.states > 2 { self.states } else { 0 };
+                let centre = self.neighbourhood.weight(0, 0) != 0 && self.neighbourhood.shape() != Shape::Weighted;
+                write!(f, "R{},C{},M{},S", self.neighbourhood.range(), states, centre as u8)?;
+                Self::write_ranges(f, &self.survival)?;
+                write!(f, ",B")?;
+                Self::write_ranges(f, &self.birth)?;
+                return write!(f, ",N{}", self.neighbourhood);
+            }
+        };
+        write!(f, "B")?;
+        for (count, _) in self.birth.iter().enumerate().filter(|(_, born)| **born) {
+            write!(f, "{}", count)?;
+        }
+        write!(f, "/S")?;
+        for (count, _) in self.survival.iter().enumerate().filter(|(_, survive)| **survive) {
+            write!(f, "{}", count)?;
+        }
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        write!(f, "{}", suffix)
+    }
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleError::Empty => write!(f, "rulestring is empty"),
+            RuleError::InvalidDigit(ch) => write!(f, "invalid neighbour count '{}' in rulestring", ch),
+            RuleError::InvalidCount(count) => write!(f, "neighbour count {} is beyond the reach of the neighbourhood", count),
+            RuleError::Malformed(rulestring) => write!(f, "malformed rulestring '{}'", rulestring),
+            RuleError::InvalidStates(states) => write!(f, "invalid number of states '{}', expected 2 to {}", states, MAX_STATES),
+            RuleError::InvalidNeighbourhood(neighbourhood) => write!(f, "invalid neighbourhood '{}'", neighbourhood),
+            RuleError::Unsupported(reason) => write!(f, "unsupported rule: {}", reason),
+        }
+    }
+}
+
+impl Error for RuleError {}
+
+impl From<RuleError> for JsValue {
+    fn from(err: RuleError) -> Self {
+        js_sys::Error::new(&err.to_string()).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cells::Cell;
+    use crate::{neighbourhood::Neighbourhood, rules::{Rule, RuleError}};
+
+    #[test]
+    fn test_conway() {
+        let conway = Rule::conway();
+        assert_eq!(conway.next_state(Cell::Alive, 1), Cell::Dead);
+        assert_eq!(conway.next_state(Cell::Alive, 2), Cell::Alive);
+        assert_eq!(conway.next_state(Cell::Alive, 3), Cell::Alive);
+        assert_eq!(conway.next_state(Cell::Alive, 4), Cell::Dead);
+        assert_eq!(conway.next_state(Cell::Alive, 5), Cell::Dead);
+        assert_eq!(conway.next_state(Cell::Alive, 6), Cell::Dead);
+        assert_eq!(conway.next_state(Cell::Alive, 7), Cell::Dead);
+        assert_eq!(conway.next_state(Cell::Alive, 8), Cell::Dead);
+
+        assert_eq!(conway.next_state(Cell::Dead, 1), Cell::Dead);
+        assert_eq!(conway.next_state(Cell::Dead, 2), Cell::Dead);
+        assert_eq!(conway.next_state(Cell::Dead, 3), Cell::Alive);
+        assert_eq!(conway.next_state(Cell::Dead, 4), Cell::Dead);
+        assert_eq!(conway.next_state(Cell::Dead, 5), Cell::Dead);
+        assert_eq!(conway.next_state(Cell::Dead, 6), Cell::Dead);
+        assert_eq!(conway.next_state(Cell::Dead, 7), Cell::Dead);
+        assert_eq!(conway.next_state(Cell::Dead, 8), Cell::Dead);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("B3/S23".parse::<Rule>(), Ok(Rule::conway()));
+        assert_eq!("b3/s23".parse::<Rule>(), Ok(Rule::conway()));
+        assert_eq!("S23/B3".parse::<Rule>(), Ok(Rule::conway()));
+        assert_eq!("23/3".parse::<Rule>(), Ok(Rule::conway()));
+        assert_eq!("B3S23".parse::<Rule>(), Ok(Rule::conway()));
+
+        let highlife: Rule = "B36/S23".parse().unwrap();
+        assert_eq!(highlife, Rule::new(&[3, 6], &[2, 3]).unwrap());
+        assert_eq!(highlife.next_state(Cell::Dead, 6), Cell::Alive);
+
+        let seeds: Rule = "B2/S".parse().unwrap();
+        assert_eq!(seeds.next_state(Cell::Alive, 2), Cell::Dead);
+        assert_eq!(seeds.next_state(Cell::Dead, 2), Cell::Alive);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!("".parse::<Rule>(), Err(RuleError::Empty));
+        assert_eq!("B39/S23".parse::<Rule>(), Err(RuleError::InvalidDigit('9')));
+        assert_eq!("B3x/S23".parse::<Rule>(), Err(RuleError::InvalidDigit('x')));
+        assert_eq!("B323".parse::<Rule>(), Err(RuleError::Malformed("B323".to_string())));
+        assert_eq!("B3/B23".parse::<Rule>(), Err(RuleError::Malformed("B3/B23".to_string())));
+        assert_eq!("B3/S2/3".parse::<Rule>(), Err(RuleError::Malformed("B3/S2/3".to_string())));
+        assert_eq!(Rule::new(&[9], &[]), Err(RuleError::InvalidDigit('9')));
+    }
+
+    #[test]
+    fn test_generations() {
+        let brians_brain: Rule = "B2/S/C3".parse().unwrap();
+        assert_eq!(brians_brain, Rule::generations(&[2], &[], 3).unwrap());
+        assert_eq!(brians_brain.states(), 3);
+        assert_eq!(brians_brain.next_state(Cell::Dead, 2), Cell::Alive);
+        assert_eq!(brians_brain.next_state(Cell::Alive, 2), Cell::new(2));
+        assert_eq!(brians_brain.next_state(Cell::new(2), 2), Cell::Dead);
+
+        let star_wars: Rule = "345/2/4".parse().unwrap();
+        assert_eq!(star_wars, Rule::generations(&[2], &[3, 4, 5], 4).unwrap());
+        assert_eq!("b2/s345/g4".parse::<Rule>().as_ref(), Ok(&star_wars));
+        assert_eq!(star_wars.next_state(Cell::Alive, 4), Cell::Alive);
+        assert_eq!(star_wars.next_state(Cell::new(2), 2), Cell::new(3));
+        assert_eq!(star_wars.next_state(Cell::new(3), 2), Cell::Dead);
+        assert_eq!("B3/S23/C2".parse::<Rule>(), Ok(Rule::conway()));
+
+        assert_eq!("B2/S/C1".parse::<Rule>(), Err(RuleError::InvalidStates("1".to_string())));
+        assert_eq!("B2/S/C257".parse::<Rule>(), Err(RuleError::InvalidStates("257".to_string())));
+        assert_eq!("B2/S/Cx".parse::<Rule>(), Err(RuleError::InvalidStates("x".to_string())));
+        assert!(Rule::conway().check_life_like().is_ok());
+        assert!(matches!(brians_brain.check_life_like(), Err(RuleError::Unsupported(_))));
+    }
+
+    #[test]
+    fn test_hexagonal() {
+        let hexagonal = Neighbourhood::hexagonal(1).unwrap();
+        let hex_life: Rule = "B2/S34H".parse().unwrap();
+        assert_eq!(hex_life, Rule::new(&[2], &[3, 4]).unwrap().with_neighbourhood(hexagonal.clone()).unwrap());
+        assert_eq!(hex_life.neighbourhood(), &hexagonal);
+        assert_eq!(hex_life.to_string(), "B2/S34H");
+        assert_eq!("34/2h".parse::<Rule>().as_ref(), Ok(&hex_life));
+        assert_eq!("B2S34H".parse::<Rule>().as_ref(), Ok(&hex_life));
+        assert_eq!("B2/S34/C3H".parse::<Rule>().unwrap().to_string(), "B2/S34/C3H");
+        assert!(!hex_life.is_life_like());
+        assert!(matches!(hex_life.check_life_like(), Err(RuleError::Unsupported(_))));
+
+        assert_eq!("B27/S34H".parse::<Rule>(), Err(RuleError::InvalidCount(7)));
+        assert_eq!(Rule::conway().with_neighbourhood(hexagonal).map(|rule| rule.to_string()), Ok("B3/S23H".to_string()));
+    }
+
+    #[test]
+    fn test_von_neumann() {
+        let rule: Rule = "B1/S4V".parse().unwrap();
+        assert_eq!(rule.neighbourhood(), &Neighbourhood::von_neumann(1).unwrap());
+        assert_eq!(rule.to_string(), "B1/S4V");
+        assert_eq!("b1s4v".parse::<Rule>().as_ref(), Ok(&rule));
+        assert!(!rule.is_life_like());
+        assert_eq!("B5/S4V".parse::<Rule>(), Err(RuleError::InvalidCount(5)));
+    }
+
+    #[test]
+    fn test_ranged() {
+        let rule: Rule = "R2,C0,M1,S2..3,5,B3,7..8,NN".parse().unwrap();
+        assert_eq!(rule.neighbourhood(), &Neighbourhood::von_neumann(2).unwrap().including_centre());
+        assert_eq!(rule.to_string(), "R2,C0,M1,S2..3,5,B3,7..8,NN");
+        assert!(rule.is_birth(8) && !rule.is_birth(6) && rule.is_survival(5) && !rule.is_survival(4));
+        assert_eq!("r2, c2, m1, s2-3, 5, b3, 7-8, nn".parse::<Rule>().as_ref(), Ok(&rule));
+        assert_eq!("R3,C0,M0,S,B4..24,NM".parse::<Rule>().unwrap().to_string(), "R3,C0,M0,S,B4..24,NM");
+        assert_eq!("R2,C3,M0,S2,B2,NH".parse::<Rule>().unwrap().states(), 3);
+        // moore neighbourhoods are the default, and range 1 ones without the cell itself fit the B/S notation
+        assert_eq!("R1,C0,M0,S2..3,B3".parse::<Rule>(), Ok(Rule::conway()));
+        assert_eq!("R1,C0,M0,S1,B1,NN".parse::<Rule>().unwrap().to_string(), "B1/S1V");
+
+        assert_eq!("R2,C0,M0,S2..3,B25,NM".parse::<Rule>(), Err(RuleError::InvalidCount(25)));
+        assert_eq!("R2,C0,M0,S2..3,B3,NN".parse::<Rule>().unwrap().with_neighbourhood(Neighbourhood::default()).map(|rule| rule.to_string()), Ok("B3/S23".to_string()));
+        assert_eq!("R2,C0,M0,S3..2,B3,NM".parse::<Rule>(), Err(RuleError::Malformed("R2,C0,M0,S3..2,B3,NM".to_string())));
+        assert_eq!("R2,C0,M2,S3,B3,NM".parse::<Rule>(), Err(RuleError::Malformed("R2,C0,M2,S3,B3,NM".to_string())));
+        assert_eq!("R,C0,M0,S3,B3,NM".parse::<Rule>(), Err(RuleError::Malformed("R,C0,M0,S3,B3,NM".to_string())));
+        assert_eq!("R2,C0,M0,S3,B3,NX".parse::<Rule>(), Err(RuleError::InvalidNeighbourhood("X".to_string())));
+        assert!(matches!("R0,C0,M0,S3,B3,NM".parse::<Rule>(), Err(RuleError::InvalidNeighbourhood(_))));
+    }
+
+    #[test]
+    fn test_weighted() {
+        let rule: Rule = "R1,C0,M0,S2..3,B3,NW121202121".parse().unwrap();
+        assert_eq!(rule.neighbourhood(), &Neighbourhood::weighted(&[1, 2, 1, 2, 0, 2, 1, 2, 1]).unwrap());
+        assert_eq!(rule.to_string(), "R1,C0,M0,S2..3,B3,NW121202121");
+        assert_eq!("R1,C0,M1,S13,B,NW121202121".parse::<Rule>(), Err(RuleError::InvalidCount(13)));
+
+        let negative: Rule = "R1,C0,M0,S1,B1,NW0101ff010001010101".parse().unwrap();
+        assert_eq!(negative.to_string(), "R1,C0,M0,S1,B1,NW0101ff010001010101");
+        assert_eq!(negative.next_state(Cell::Dead, -1), Cell::Dead);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Rule::conway().to_string(), "B3/S23");
+        assert_eq!("/2/3".parse::<Rule>().unwrap().to_string(), "B2/S/C3");
+        assert_eq!("34678/3678".parse::<Rule>().unwrap().to_string(), "B3678/S34678");
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::{HashMap, HashSet};
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    cells::Cell, formats::{rle, ParseError, Pattern}, packed::evolve_word, rules::{Rule, RuleError}, universe::{cell_count, SizeError},
+};
+
+const TILE_SIZE: i64 = 64;
+
+/** a 64x64 block of cells, row `r` is stored in word `r` and column `c` in bit `c` of it */
+type Tile = [u64; TILE_SIZE as usize];
+
+const EMPTY_TILE: Tile = [0; TILE_SIZE as usize];
+
+/**
+ * an unbounded universe which only stores the tiles containing living cells
+ *
+ * cells are addressed by signed `row`, `col` coordinates and grouped into 64x64 tiles
+ * keyed by `(row / 64, col / 64)`. tiles are allocated as soon as a cell is born in them
+ * and dropped again as soon as all of their cells are dead
+ */
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct SparseUniverse {
+    tiles: HashMap<(i64, i64), Box<Tile>>,
+    rule: Rule,
+    generation: u64,
+}
+
+impl SparseUniverse {
+    pub fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    pub fn replace_rule(&mut self, rule: Rule) -> Result<(), RuleError> {
+        rule.check_unbounded()?;
+        rule.check_life_like()?;
+        self.rule = rule;
+        Ok(())
+    }
+
+    /**
+     * bring the living cells of a pattern to life with its top left corner at `row`, `col`,
+     * nothing is changed if any of them would be beyond the range of `i64`
+     */
+    pub fn insert_pattern(&mut self, pattern: &Pattern, row: i64, col: i64) -> Result<(), ParseError> {
+        let cells: Option<Vec<[i64; 2]>> = pattern.cells.iter()
+            .map(|[r, c]| Some([row.checked_add(*r as i64)?, col.checked_add(*c as i64)?]))
+            .collect();
+        let cells = cells.ok_or(ParseError::DoesNotFit { width: pattern.width, height: pattern.height })?;
+        for [row, col] in cells {
+            self.set_cell(row, col, true);
+        }
+        Ok(())
+    }
+
+    /** the living cells as `[row, col]` coordinates */
+    pub fn living_cells(&self) -> Vec<[i64; 2]> {
+        let mut living = vec![];
+        for (&(tile_row, tile_col), tile) in &self.tiles {
+            for (row, word) in tile.iter().enumerate() {
+                let mut word = *word;
+                while word != 0 {
+                    let col = word.trailing_zeros() as i64;
+                    living.push([tile_row * TILE_SIZE + row as i64, tile_col * TILE_SIZE + col]);
+                    word &= word - 1;
+                }
+            }
+        }
+        living.sort_unstable();
+        living
+    }
+
+    /** the smallest `[top, left, bottom, right]` rectangle containing every living cell */
+    pub fn bounding_box(&self) -> Option<[i64; 4]> {
+        let living = self.living_cells();
+        let first = living.first()?;
+        let mut bounds = [first[0], first[1], first[0], first[1]];
+        for [row, col] in &living {
+            bounds = [bounds[0].min(*row), bounds[1].min(*col), bounds[2].max(*row), bounds[3].max(*col)];
+        }
+        Some(bounds)
+    }
+
+    pub fn tile_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn next_epoch(&mut self) {
+        let mut active = HashSet::new();
+        for &(tile_row, tile_col) in self.tiles.keys() {
+            for row_delta in -1..=1 {
+                for col_delta in -1..=1 {
+                    active.insert((tile_row + row_delta, tile_col + col_delta));
+                }
+            }
+        }
+
+        let mut next_tiles = HashMap::with_capacity(active.len());
+        for key in active {
+            let next = self.next_tile(key);
+            if next.iter().any(|word| *word != 0) {
+                next_tiles.insert(key, Box::new(next));
+            }
+        }
+
+        self.tiles = next_tiles;
+        self.generation += 1;
+    }
+
+    fn next_tile(&self, (tile_row, tile_col): (i64, i64)) -> Tile {
+        // the 3x3 block of tiles around the one being computed
+        let mut around = [[&EMPTY_TILE; 3]; 3];
+        for (row_delta, tiles) in around.iter_mut().enumerate() {
+            for (col_delta, tile) in tiles.iter_mut().enumerate() {
+                let key = (tile_row + row_delta as i64 - 1, tile_col + col_delta as i64 - 1);
+                if let Some(found) = self.tiles.get(&key) {
+                    *tile = found;
+                }
+            }
+        }
+        if around.iter().flatten().all(|tile| std::ptr::eq(*tile, &EMPTY_TILE)) {
+            return EMPTY_TILE;
+        }
+
+        // a row of the tile, shifted to line up the west and east neightbours
+        let shifted = |row: i64| {
+            let (block_row, row) = (row.div_euclid(TILE_SIZE) as usize, row.rem_euclid(TILE_SIZE) as usize);
+            let [west, centre, east] = around[block_row];
+            let word = centre[row];
+            (word << 1 | west[row] >> 63, word, word >> 1 | east[row] << 63)
+        };
+
+        let mut next = EMPTY_TILE;
+        for (row, next_word) in next.iter_mut().enumerate() {
+            let row = row as i64 + TILE_SIZE;
+            let (nw, n, ne) = shifted(row - 1);
+            let (w, alive, e) = shifted(row);
+            let (sw, s, se) = shifted(row + 1);
+            *next_word = evolve_word(&self.rule, alive, [nw, n, ne, w, e, sw, s, se]);
+        }
+        next
+    }
+
+    fn locate(row: i64, col: i64) -> ((i64, i64), usize, u32) {
+        let key = (row.div_euclid(TILE_SIZE), col.div_euclid(TILE_SIZE));
+        (key, row.rem_euclid(TILE_SIZE) as usize, col.rem_euclid(TILE_SIZE) as u32)
+    }
+}
+
+#[wasm_bindgen]
+impl SparseUniverse {
+    pub fn new() -> SparseUniverse {
+        SparseUniverse::default()
+    }
+
+    /** replace the rule used by `tick`, rules which are not life-like or give birth on 0 neightbours are rejected */
+    pub fn set_rule(&mut self, rulestring: &str) -> Result<(), RuleError> {
+        self.replace_rule(rulestring.parse()?)
+    }
+
+    pub fn rulestring(&self) -> String {
+        self.rule.to_string()
+    }
+
+    /** bring a run length encoded pattern to life with its top left corner at `row`, `col` */
+    pub fn load_rle_at(&mut self, rle: &str, row: i64, col: i64) -> Result<(), ParseError> {
+        let pattern = rle::parse(rle)?;
+        pattern.check_states(&self.rule)?;
+        self.insert_pattern(&pattern, row, col)
+    }
+
+    pub fn set_cell(&mut self, row: i64, col: i64, alive: bool) {
+        let (key, row, col) = Self::locate(row, col);
+        if alive {
+            self.tiles.entry(key).or_insert_with(|| Box::new(EMPTY_TILE))[row] |= 1 << col;
+        } else if let Some(tile) = self.tiles.get_mut(&key) {
+            tile[row] &= !(1 << col);
+            if tile.iter().all(|word| *word == 0) {
+                self.tiles.remove(&key);
+            }
+        }
+    }
+
+    pub fn get_cell(&self, row: i64, col: i64) -> bool {
+        let (key, row, col) = Self::locate(row, col);
+        self.tiles.get(&key).is_some_and(|tile| tile[row] >> col & 1 == 1)
+    }
+
+    pub fn population(&self) -> u64 {
+        self.tiles.values().flat_map(|tile| tile.iter()).map(|word| word.count_ones() as u64).sum()
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn tick(&mut self) {
+        self.next_epoch();
+    }
+
+    /**
+     * copy the `width` x `height` window whose top left corner is at `top`, `left`
+     * into the flattened one byte per cell layout of `Universe::cells`,
+     * which can be no larger than a universe
+     */
+    pub fn viewport(&self, top: i64, left: i64, width: u32, height: u32) -> Result<Vec<u8>, SizeError> {
+        let count = cell_count(width, height).ok_or(SizeError { width, height })?;
+        let mut window = vec![u8::from(Cell::Dead); count];
+        for (&(tile_row, tile_col), tile) in &self.tiles {
+            let (tile_top, tile_left) = (tile_row * TILE_SIZE, tile_col * TILE_SIZE);
+            let rows = tile_top.max(top)..(tile_top + TILE_SIZE).min(top.saturating_add(height as i64));
+            let cols = tile_left.max(left)..(tile_left + TILE_SIZE).min(left.saturating_add(width as i64));
+            for row in rows {
+                let word = tile[(row - tile_top) as usize];
+                for col in cols.clone() {
+                    if word >> (col - tile_left) & 1 == 1 {
+                        let index = (row - top) as usize * width as usize + (col - left) as usize;
+                        window[index] = Cell::Alive.into();
+                    }
+                }
+            }
+        }
+        Ok(window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{formats::ParseError, random::soup, rules::RuleError, sparse::SparseUniverse, universe::{SizeError, Universe}};
+
+    const GLIDER: [[i64; 2]; 5] = [[0, 1], [1, 2], [2, 0], [2, 1], [2, 2]];
+
+    #[test]
+    fn test_set_cell() {
+        let mut universe = SparseUniverse::new();
+        universe.set_cell(-1, -1, true);
+        universe.set_cell(1_000_000_000_000, 64, true);
+        assert!(universe.get_cell(-1, -1));
+        assert!(!universe.get_cell(-1, 0));
+        assert_eq!(universe.population(), 2);
+        assert_eq!(universe.tile_count(), 2);
+
+        universe.set_cell(-1, -1, false);
+        assert_eq!(universe.tile_count(), 1);
+        assert_eq!(universe.living_cells(), vec![[1_000_000_000_000, 64]]);
+    }
+
+    #[test]
+    fn test_glider_travels_without_edges() {
+        let mut universe = SparseUniverse::new();
+        for [row, col] in GLIDER {
+            universe.set_cell(row - 70, col - 70, true);
+        }
+        for _ in 0..4 * 300 {
+            universe.next_epoch();
+        }
+        assert_eq!(universe.generation(), 1200);
+        assert_eq!(universe.population(), 5);
+        assert_eq!(universe.bounding_box(), Some([230, 230, 232, 232]));
+        assert!(universe.tile_count() <= 4);
+        for [row, col] in GLIDER {
+            assert!(universe.get_cell(row + 230, col + 230));
+        }
+    }
+
+    #[test]
+    fn test_matches_dense() {
+        // a soup straddling the tiles around the origin, far enough from the torus edges
+        let mut sparse = SparseUniverse::new();
+        let mut dense = Universe::new(200, 200);
+        let mut living = vec![];
+        for [row, col] in soup(11, 40, 40, 1.0 / 3.0) {
+            sparse.set_cell(row as i64 - 20, col as i64 - 20, true);
+            living.push([row + 80, col + 80]);
+        }
+        dense.init_cells(living);
+
+        for _ in 0..50 {
+            sparse.next_epoch();
+            dense.next_epoch();
+        }
+        assert_eq!(sparse.viewport(-100, -100, 200, 200).unwrap(), dense.cells_to_arr());
+    }
+
+    #[test]
+    fn test_viewport() {
+        let mut universe = SparseUniverse::new();
+        universe.set_cell(-1, 0, true);
+        universe.set_cell(0, 1, true);
+        assert_eq!(universe.viewport(-1, -1, 3, 2).unwrap(), vec![0, 1, 0, 0, 0, 1]);
+        assert_eq!(universe.viewport(0, 0, u32::MAX, u32::MAX), Err(SizeError { width: u32::MAX, height: u32::MAX }));
+        assert_eq!(universe.viewport(i64::MAX, i64::MAX, 2, 2).unwrap(), vec![0; 4]);
+    }
+
+    #[test]
+    fn test_rule() {
+        let mut universe = SparseUniverse::new();
+        assert!(matches!(universe.set_rule("B0/S8"), Err(RuleError::Unsupported(_))));
+        assert!(matches!(universe.set_rule("B2/S/C3"), Err(RuleError::Unsupported(_))));
+        universe.set_rule("B36/S23").unwrap();
+        assert_eq!(universe.rulestring(), "B36/S23");
+    }
+
+    #[test]
+    fn test_load_rle_at() {
+        let mut universe = SparseUniverse::new();
+        universe.load_rle_at("x = 3, y = 3\nbo$2bo$3o!", -10, -10).unwrap();
+        assert_eq!(universe.living_cells(), vec![[-10, -9], [-9, -8], [-8, -10], [-8, -9], [-8, -8]]);
+        assert_eq!(universe.load_rle_at("x = 2, y = 1\nAB!", 0, 0), Err(ParseError::InvalidState { state: 2, states: 2 }));
+        assert_eq!(universe.load_rle_at("x = 2, y = 1\nbo!", 0, i64::MAX), Err(ParseError::DoesNotFit { width: 2, height: 1 }));
+        assert_eq!(universe.population(), 5);
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{cells::Cell, neighbourhood::{Neighbourhood, Shape}, topology::Topology};
+
+/**
+ * a summed-area table of the living cells, for counting the large moore and von neumann neighbourhoods
+ * of larger than life rules (e.g. `R5,C0,M1,S34..58,B34..45,NM`) in constant time per cell
+ *
+ * the grid is padded by `range` cells on every side with whatever the topology shows beyond the edges
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SummedArea {
+    range: u32,
+    table: Table,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Table {
+    /** `sums[r * padded_width + c]` holds the living cells of the padded rows `..r` and columns `..c` */
+    Squares { padded_width: usize, sums: Vec<u32> },
+    /** the living cells in the diamond around every cell, in row-major order */
+    Diamonds { width: usize, counts: Vec<u32> },
+}
+
+impl SummedArea {
+    /** whether the neighbourhood is a whole square or diamond of equally weighted cells, the only kinds the table can count */
+    pub fn supports(neighbourhood: &Neighbourhood) -> bool {
+        matches!(neighbourhood.shape(), Shape::Moore | Shape::VonNeumann)
+    }
+
+    pub fn new(cells: &[Cell], width: u32, height: u32, neighbourhood: &Neighbourhood, topology: Topology) -> SummedArea {
+        let range = neighbourhood.range();
+        let table = match neighbourhood.shape() {
+            Shape::VonNeumann => diamonds(cells, width, height, range, topology),
+            _ => squares(cells, width, height, range, topology),
+        };
+        SummedArea { range, table }
+    }
+
+    /** the living cells in the square or diamond reaching `range` cells around `row`, `col`, the cell itself included */
+    pub fn within(&self, row: u32, col: u32) -> u32 {
+        match &self.table {
+            Table::Squares { padded_width, sums } => {
+                // the square covers the padded rows and columns `row..=row + 2 * range`
+                let side = 2 * self.range as usize + 1;
+                let (top, left) = (row as usize, col as usize);
+                let (bottom, right) = (top + side, left + side);
+                let at = |r: usize, c: usize| sums[r * padded_width + c];
+                at(bottom, right) + at(top, left) - at(top, right) - at(bottom, left)
+            }
+            Table::Diamonds { width, counts } => counts[row as usize * width + col as usize],
+        }
+    }
+
+    /** the living neightbours of `row`, `col`, leaving the cell out unless it counts itself */
+    pub fn count(&self, neighbourhood: &Neighbourhood, row: u32, col: u32, cell: Cell) -> i32 {
+        let within = self.within(row, col) as i32;
+        within - (cell.is_alive() && neighbourhood.weight(0, 0) == 0) as i32
+    }
+}
+
+/** whether the cell `row`, `col` of the grid padded by `range` is alive */
+fn padded(cells: &[Cell], width: u32, height: u32, range: u32, topology: Topology) -> impl Fn(usize, usize) -> u32 + '_ {
+    move |row, col| {
+        let (row, col) = (row as i64 - range as i64, col as i64 - range as i64);
+        topology.resolve(row, col, width, height)
+            .map_or(0, |(row, col)| cells[(row * width + col) as usize].is_alive() as u32)
+    }
+}
+
+fn squares(cells: &[Cell], width: u32, height: u32, range: u32, topology: Topology) -> Table {
+    let alive = padded(cells, width, height, range, topology);
+    let padded_width = width as usize + 2 * range as usize + 1;
+    let padded_height = height as usize + 2 * range as usize + 1;
+    let mut sums = vec![0; padded_width * padded_height];
+    for r in 1..padded_height {
+        let mut row_sum = 0;
+        for c in 1..padded_width {
+            row_sum += alive(r - 1, c - 1);
+            sums[r * padded_width + c] = sums[(r - 1) * padded_width + c] + row_sum;
+        }
+    }
+    Table::Squares { padded_width, sums }
+}
+
+/**
+ * slide the diamond along each row, and down the first column, adding the cells on its leading edges
+ * and taking away those on its trailing edges.
+ * the edges are diagonal, so they are summed from running totals along both directions of diagonals
+ */
+fn diamonds(cells: &[Cell], width: u32, height: u32, range: u32, topology: Topology) -> Table {
+    let alive = padded(cells, width, height, range, topology);
+    let range = range as usize;
+    let (padded_width, padded_height) = (width as usize + 2 * range, height as usize + 2 * range);
+
+    // `down[i * padded_width + j]` sums the padded cells from `i, j` up and to the left, `up` those up and to the right
+    let mut down = vec![0; padded_width * padded_height];
+    let mut up = vec![0; padded_width * padded_height];
+    for i in 0..padded_height {
+        for j in 0..padded_width {
+            let cell = alive(i, j);
+            down[i * padded_width + j] = cell + if i > 0 && j > 0 { down[(i - 1) * padded_width + j - 1] } else { 0 };
+            up[i * padded_width + j] = cell + if i > 0 && j + 1 < padded_width { up[(i - 1) * padded_width + j + 1] } else { 0 };
+        }
+    }
+    // the `length` cells from `i, j` going down and to the right, or down and to the left
+    let rightwards = |i: usize, j: usize, length: usize| match length {
+        0 => 0,
+        _ if i > 0 && j > 0 => down[(i + length - 1) * padded_width + j + length - 1] - down[(i - 1) * padded_width + j - 1],
+        _ => down[(i + length - 1) * padded_width + j + length - 1],
+    };
+    let leftwards = |i: usize, j: usize, length: usize| match length {
+        0 => 0,
+        _ if i > 0 && j + 1 < padded_width => up[(i + length - 1) * padded_width + j + 1 - length] - up[(i - 1) * padded_width + j + 1],
+        _ => up[(i + length - 1) * padded_width + j + 1 - length],
+    };
+
+    // the diamond around the top left cell, which sits at `range`, `range` in the padded grid
+    let mut first = (0..=2 * range)
+        .map(|i| {
+            let reach = range - i.abs_diff(range);
+            (range - reach..=range + reach).map(|j| alive(i, j)).sum::<u32>()
+        })
+        .sum::<u32>();
+    let mut counts = Vec::with_capacity(width as usize * height as usize);
+    for y in range..range + height as usize {
+        if y > range {
+            // the bottom edge of the diamond one row down, and the top edge of the one above
+            first += rightwards(y, 0, range + 1) + leftwards(y, 2 * range, range);
+            first -= leftwards(y - 1 - range, range, range + 1) + rightwards(y - range, range + 1, range);
+        }
+        let mut count = first;
+        counts.push(count);
+        for x in range..range + width as usize - 1 {
+            // the right edge of the diamond one column further, and the left edge of this one
+            count += rightwards(y - range, x + 1, range + 1) + leftwards(y + 1, x + range, range);
+            count -= leftwards(y - range, x, range + 1) + rightwards(y + 1, x + 1 - range, range);
+            counts.push(count);
+        }
+    }
+    Table::Diamonds { width: width as usize, counts }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{cells::Cell, neighbourhood::Neighbourhood, random::soup, summed::SummedArea, topology::Topology};
+
+    #[test]
+    fn test_square() {
+        // a 4x3 grid with its top left and bottom right cells alive
+        let mut cells = vec![Cell::Dead; 12];
+        cells[0] = Cell::Alive;
+        cells[11] = Cell::Alive;
+        let moore = |range: u32| Neighbourhood::moore(range).unwrap();
+
+        let torus = SummedArea::new(&cells, 4, 3, &moore(1), Topology::Torus);
+        assert_eq!(torus.within(0, 0), 2);
+        assert_eq!(torus.within(1, 1), 1);
+        assert_eq!(torus.within(1, 2), 1);
+
+        let bounded = SummedArea::new(&cells, 4, 3, &moore(1), Topology::Bounded);
+        assert_eq!(bounded.within(0, 0), 1);
+        assert_eq!(bounded.within(2, 0), 0);
+
+        // a range wider than the torus sees its cells more than once, like counting them one by one does
+        let wide = SummedArea::new(&cells, 4, 3, &moore(2), Topology::Torus);
+        assert_eq!(wide.within(1, 1), 6);
+    }
+
+    #[test]
+    fn test_diamond() {
+        let (width, height) = (13, 9);
+        let mut cells = vec![Cell::Dead; width as usize * height as usize];
+        for [row, col] in soup(3, width, height, 0.5) {
+            cells[(row * width + col) as usize] = Cell::Alive;
+        }
+        for topology in [Topology::Torus, Topology::Bounded, Topology::KleinBottle] {
+            // ranges up to wider than the grid
+            for range in [1, 2, 5, 14] {
+                let neighbourhood = Neighbourhood::von_neumann(range).unwrap();
+                let summed = SummedArea::new(&cells, width, height, &neighbourhood, topology);
+                for row in 0..height {
+                    for col in 0..width {
+                        let cell = cells[(row * width + col) as usize];
+                        let expected = neighbourhood.cells().iter()
+                            .filter_map(|(r, c, _)| topology.resolve(row as i64 + r, col as i64 + c, width, height))
+                            .filter(|(r, c)| cells[(r * width + c) as usize].is_alive())
+                            .count() as i32;
+                        assert_eq!(summed.count(&neighbourhood, row, col, cell), expected, "{:?} range {} at {}, {}", topology, range, row, col);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use std::{collections::VecDeque, error::Error, fmt, mem::{size_of, size_of_val}};
+
+use wasm_bindgen::prelude::*;
+
+use crate::{cells::Cell, history::Diff};
+
+/** the memory `Timeline` may use unless told otherwise, in bytes */
+pub const DEFAULT_BUDGET: usize = 64 << 20;
+
+/** how many generations apart full snapshots are taken unless told otherwise */
+pub const DEFAULT_INTERVAL: u32 = 64;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SeekError {
+    /** the generation was forgotten to stay within the budget, or has not been reached yet */
+    NotRecorded { generation: u64, earliest: u64, latest: u64 },
+}
+
+/**
+ * every generation of a run from `base` up to the latest one, stored as a full snapshot
+ * of the cells every `interval` generations plus the diff leading to each generation.
+ * once more than `budget` bytes are used the oldest snapshots are forgotten,
+ * a budget of 0 turns the timeline off
+ */
+#[derive(Clone, Debug)]
+pub struct Timeline {
+    keyframes: VecDeque<(u64, Vec<Cell>)>,
+    /** `deltas[i]` leads from generation `base + i` to `base + i + 1` */
+    deltas: VecDeque<Diff>,
+    base: u64,
+    interval: u32,
+    budget: usize,
+    used: usize,
+}
+
+impl Timeline {
+    /** a timeline starting at `generation` */
+    pub fn new(generation: u64, cells: &[Cell]) -> Timeline {
+        let mut timeline = Timeline::with_budget(DEFAULT_BUDGET);
+        timeline.reset(generation, cells);
+        timeline
+    }
+
+    /** a timeline which records nothing until it is given a budget and an edit */
+    pub fn disabled() -> Timeline {
+        Timeline::with_budget(0)
+    }
+
+    fn with_budget(budget: usize) -> Timeline {
+        Timeline {
+            keyframes: VecDeque::new(),
+            deltas: VecDeque::new(),
+            base: 0,
+            interval: DEFAULT_INTERVAL,
+            budget,
+            used: 0,
+        }
+    }
+
+    pub fn interval(&self) -> u32 {
+        self.interval
+    }
+
+    /** take snapshots every `interval` generations from now on */
+    pub fn set_interval(&mut self, interval: u32) {
+        self.interval = interval.max(1);
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        if budget == 0 {
+            self.clear();
+        }
+        self.trim();
+    }
+
+    /** the memory used by snapshots and diffs, in bytes */
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    /** the first and last generation that can be reconstructed */
+    pub fn range(&self) -> Option<(u64, u64)> {
+        self.keyframes.front()?;
+        Some((self.base, self.latest()))
+    }
+
+    /** record the step from `generation` to the next one, dropping any other future recorded before */
+    pub fn step(&mut self, generation: u64, diff: &Diff, cells: &[Cell]) {
+        if self.budget == 0 {
+            return;
+        }
+        if !self.contains(generation) {
+            self.reset(generation + 1, cells);
+            return;
+        }
+        let offset = (generation - self.base) as usize;
+        if self.deltas.get(offset) == Some(diff) {
+            // stepping again along the recorded future
+            return;
+        }
+
+        self.truncate(generation);
+        self.used += diff.size();
+        self.deltas.push_back(diff.clone());
+        if (generation + 1).is_multiple_of(self.interval as u64) {
+            self.push_keyframe(generation + 1, cells);
+        }
+        self.trim();
+    }
+
+    /** record that the cells of `generation` were edited, which makes the recorded future obsolete */
+    pub fn edit(&mut self, generation: u64, cells: &[Cell]) {
+        if self.budget == 0 {
+            return;
+        }
+        if !self.contains(generation) {
+            self.reset(generation, cells);
+            return;
+        }
+
+        self.truncate(generation);
+        if self.keyframes.back().is_some_and(|(keyframe, _)| *keyframe == generation) {
+            self.pop_keyframe();
+        }
+        self.push_keyframe(generation, cells);
+        self.trim();
+    }
+
+    /** the cells of a recorded generation, from the closest snapshot before it and the diffs following it */
+    pub fn reconstruct(&self, generation: u64) -> Result<Vec<Cell>, SeekError> {
+        if !self.contains(generation) {
+            let (earliest, latest) = self.range().unwrap_or((generation, generation));
+            return Err(SeekError::NotRecorded { generation, earliest, latest });
+        }
+
+        let closest = self.keyframes.partition_point(|(keyframe, _)| *keyframe <= generation) - 1;
+        let (keyframe, snapshot) = &self.keyframes[closest];
+        let mut cells = snapshot.clone();
+        let deltas = (keyframe - self.base) as usize..(generation - self.base) as usize;
+        for delta in self.deltas.range(deltas) {
+            delta.apply(&mut cells);
+        }
+        Ok(cells)
+    }
+
+    pub fn clear(&mut self) {
+        self.keyframes.clear();
+        self.deltas.clear();
+        self.used = 0;
+    }
+
+    fn latest(&self) -> u64 {
+        self.base + self.deltas.len() as u64
+    }
+
+    fn contains(&self, generation: u64) -> bool {
+        self.range().is_some_and(|(earliest, latest)| (earliest..=latest).contains(&generation))
+    }
+
+    fn reset(&mut self, generation: u64, cells: &[Cell]) {
+        self.clear();
+        self.base = generation;
+        self.push_keyframe(generation, cells);
+    }
+
+    /** forget everything recorded after `generation` */
+    fn truncate(&mut self, generation: u64) {
+        let kept = (generation - self.base) as usize;
+        self.used -= self.deltas.drain(kept..).map(|delta| delta.size()).sum::<usize>();
+        while self.keyframes.back().is_some_and(|(keyframe, _)| *keyframe > generation) {
+            self.pop_keyframe();
+        }
+    }
+
+    fn push_keyframe(&mut self, generation: u64, cells: &[Cell]) {
+        self.used += keyframe_size(cells);
+        self.keyframes.push_back((generation, cells.to_vec()));
+    }
+
+    fn pop_keyframe(&mut self) {
+        if let Some((_, cells)) = self.keyframes.pop_back() {
+            self.used -= keyframe_size(&cells);
+        }
+    }
+
+    /**
+     * forget the oldest snapshots and the diffs following them. once a single snapshot is left
+     * it is moved forward one diff at a time instead, so only the snapshot itself can outgrow the budget
+     */
+    fn trim(&mut self) {
+        while self.used > self.budget && self.keyframes.len() > 1 {
+            let (_, cells) = self.keyframes.pop_front().unwrap();
+            self.used -= keyframe_size(&cells);
+            let next = self.keyframes[0].0;
+            let forgotten = (next - self.base) as usize;
+            self.used -= self.deltas.drain(..forgotten).map(|delta| delta.size()).sum::<usize>();
+            self.base = next;
+        }
+        while self.used > self.budget {
+            let (Some(delta), Some((keyframe, cells))) = (self.deltas.pop_front(), self.keyframes.front_mut()) else {
+                return;
+            };
+            delta.apply(cells);
+            *keyframe += 1;
+            self.base += 1;
+            self.used -= delta.size();
+        }
+    }
+}
+
+fn keyframe_size(cells: &[Cell]) -> usize {
+    size_of::<(u64, Vec<Cell>)>() + size_of_val(cells)
+}
+
+impl fmt::Display for SeekError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeekError::NotRecorded { generation, earliest, latest } => write!(
+                f, "generation {} is not recorded, the timeline covers generations {} to {}", generation, earliest, latest
+            ),
+        }
+    }
+}
+
+impl Error for SeekError {}
+
+impl From<SeekError> for JsValue {
+    fn from(err: SeekError) -> Self {
+        js_sys::Error::new(&err.to_string()).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{cells::Cell, history::Diff, timeline::{SeekError, Timeline}};
+
+    /** a row of 4 cells in which only cell `generation % 4` is alive */
+    fn state(generation: u64) -> Vec<Cell> {
+        (0..4).map(|idx| if idx == generation % 4 { Cell::Alive } else { Cell::Dead }).collect()
+    }
+
+    fn run(timeline: &mut Timeline, from: u64, to: u64) {
+        for generation in from..to {
+            let mut diff = Diff::between(&state(generation), &state(generation + 1));
+            diff.generations = 1;
+            timeline.step(generation, &diff, &state(generation + 1));
+        }
+    }
+
+    #[test]
+    fn test_reconstruct() {
+        let mut timeline = Timeline::new(0, &state(0));
+        timeline.set_interval(3);
+        run(&mut timeline, 0, 10);
+        assert_eq!(timeline.range(), Some((0, 10)));
+        for generation in 0..=10 {
+            assert_eq!(timeline.reconstruct(generation), Ok(state(generation)));
+        }
+        assert_eq!(timeline.reconstruct(11), Err(SeekError::NotRecorded { generation: 11, earliest: 0, latest: 10 }));
+    }
+
+    #[test]
+    fn test_edit_truncates() {
+        let mut timeline = Timeline::new(0, &state(0));
+        run(&mut timeline, 0, 10);
+        timeline.edit(4, &state(0));
+        assert_eq!(timeline.range(), Some((0, 4)));
+        assert_eq!(timeline.reconstruct(4), Ok(state(0)));
+        assert_eq!(timeline.reconstruct(3), Ok(state(3)));
+
+        // stepping along the recorded run keeps it, stepping differently replaces the rest of it
+        run(&mut timeline, 1, 2);
+        assert_eq!(timeline.range(), Some((0, 4)));
+        let mut diff = Diff::between(&state(1), &state(3));
+        diff.generations = 1;
+        timeline.step(1, &diff, &state(3));
+        assert_eq!(timeline.range(), Some((0, 2)));
+        assert_eq!(timeline.reconstruct(2), Ok(state(3)));
+    }
+
+    #[test]
+    fn test_budget() {
+        let mut timeline = Timeline::new(0, &state(0));
+        timeline.set_interval(2);
+        run(&mut timeline, 0, 100);
+        let full = timeline.used();
+
+        timeline.set_budget(full / 2);
+        let (earliest, latest) = timeline.range().unwrap();
+        assert!(earliest > 0 && earliest % 2 == 0);
+        assert_eq!(latest, 100);
+        assert!(timeline.used() <= full / 2);
+        assert_eq!(timeline.reconstruct(earliest), Ok(state(earliest)));
+
+        timeline.set_budget(0);
+        run(&mut timeline, 100, 110);
+        assert_eq!(timeline.range(), None);
+    }
+
+    #[test]
+    fn test_budget_single_keyframe() {
+        // with no snapshot but the first, the diffs alone have to be forgotten to stay within the budget
+        let mut timeline = Timeline::new(0, &state(0));
+        timeline.set_interval(1000);
+        run(&mut timeline, 0, 100);
+        let full = timeline.used();
+
+        timeline.set_budget(full / 2);
+        let (earliest, latest) = timeline.range().unwrap();
+        assert!(earliest > 0);
+        assert_eq!(latest, 100);
+        assert!(timeline.used() <= full / 2);
+        for generation in [earliest, 50.max(earliest), 100] {
+            assert_eq!(timeline.reconstruct(generation), Ok(state(generation)));
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use wasm_bindgen::prelude::*;
+
+/**
+ * how the edges of a finite universe are glued together,
+ * i.e. what a cell next to the edge sees when it looks beyond it
+ */
+#[wasm_bindgen]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Topology {
+    /** opposite edges are joined, leaving the grid at the right brings you back at the left */
+    #[default]
+    Torus = 0,
+    /** everything beyond the edges is permanently dead */
+    Bounded = 1,
+    /** the edges reflect, the cells beyond an edge mirror the cells just inside it */
+    Mirror = 2,
+    /** left and right edges are joined as a torus, top and bottom edges are joined with a twist */
+    KleinBottle = 3,
+    /** both pairs of opposite edges are joined with a twist (the real projective plane) */
+    CrossSurface = 4,
+}
+
+impl Topology {
+    /**
+     * map a (possibly out of range) coordinate onto the grid
+     * returns `None` if the coordinate falls onto a permanently dead cell
+     *
+     * rows are resolved before columns, so a corner of a twisted surface
+     * is reached by crossing the top/bottom edge first
+     */
+    pub fn resolve(self, row: i64, col: i64, width: u32, height: u32) -> Option<(u32, u32)> {
+        let (row, row_twisted) = self.resolve_row(row, height)?;
+        let col = if row_twisted { flip(col, width) } else { col };
+
+        match self {
+            Topology::Torus | Topology::KleinBottle => Some((row, wrap(col, width).0)),
+            Topology::Bounded => in_range(col, width).map(|col| (row, col)),
+            Topology::Mirror => Some((row, reflect(col, width))),
+            Topology::CrossSurface => {
+                let (col, col_twisted) = wrap(col, width);
+                let row = if col_twisted { flip(row as i64, height) as u32 } else { row };
+                Some((row, col))
+            }
+        }
+    }
+
+    /**
+     * map a (possibly out of range) row onto the grid for an in-range column
+     * returns the resolved row and whether the columns of that row are seen mirrored
+     */
+    pub fn resolve_row(self, row: i64, height: u32) -> Option<(u32, bool)> {
+        match self {
+            Topology::Torus => Some((wrap(row, height).0, false)),
+            Topology::Bounded => in_range(row, height).map(|row| (row, false)),
+            Topology::Mirror => Some((reflect(row, height), false)),
+            Topology::KleinBottle | Topology::CrossSurface => Some(wrap(row, height)),
+        }
+    }
+}
+
+/** wrap around a circle of `len`, also telling whether an edge was crossed an odd number of times */
+fn wrap(coord: i64, len: u32) -> (u32, bool) {
+    let len = len as i64;
+    (coord.rem_euclid(len) as u32, coord.div_euclid(len) % 2 != 0)
+}
+
+fn reflect(coord: i64, len: u32) -> u32 {
+    let period = 2 * len as i64;
+    let folded = coord.rem_euclid(period);
+    if folded < len as i64 { folded as u32 } else { (period - 1 - folded) as u32 }
+}
+
+fn flip(coord: i64, len: u32) -> i64 {
+    len as i64 - 1 - coord
+}
+
+fn in_range(coord: i64, len: u32) -> Option<u32> {
+    if coord >= 0 && coord < len as i64 { Some(coord as u32) } else { None }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::topology::Topology;
+
+    #[test]
+    fn test_inside() {
+        for topology in [Topology::Torus, Topology::Bounded, Topology::Mirror, Topology::KleinBottle, Topology::CrossSurface] {
+            assert_eq!(topology.resolve(1, 2, 4, 3), Some((1, 2)));
+        }
+    }
+
+    #[test]
+    fn test_torus() {
+        assert_eq!(Topology::Torus.resolve(-1, 0, 4, 3), Some((2, 0)));
+        assert_eq!(Topology::Torus.resolve(0, 4, 4, 3), Some((0, 0)));
+        assert_eq!(Topology::Torus.resolve(-1, -1, 4, 3), Some((2, 3)));
+    }
+
+    #[test]
+    fn test_bounded() {
+        assert_eq!(Topology::Bounded.resolve(-1, 0, 4, 3), None);
+        assert_eq!(Topology::Bounded.resolve(0, 4, 4, 3), None);
+        assert_eq!(Topology::Bounded.resolve(2, 3, 4, 3), Some((2, 3)));
+    }
+
+    #[test]
+    fn test_mirror() {
+        assert_eq!(Topology::Mirror.resolve(-1, 1, 4, 3), Some((0, 1)));
+        assert_eq!(Topology::Mirror.resolve(3, 4, 4, 3), Some((2, 3)));
+        assert_eq!(Topology::Mirror.resolve(1, -2, 4, 3), Some((1, 1)));
+    }
+
+    #[test]
+    fn test_klein_bottle() {
+        // crossing the top/bottom edge mirrors the column
+        assert_eq!(Topology::KleinBottle.resolve(-1, 0, 4, 3), Some((2, 3)));
+        assert_eq!(Topology::KleinBottle.resolve(3, 1, 4, 3), Some((0, 2)));
+        // crossing the left/right edge does not
+        assert_eq!(Topology::KleinBottle.resolve(1, 4, 4, 3), Some((1, 0)));
+        assert_eq!(Topology::KleinBottle.resolve(-1, -1, 4, 3), Some((2, 0)));
+    }
+
+    #[test]
+    fn test_cross_surface() {
+        assert_eq!(Topology::CrossSurface.resolve(-1, 0, 4, 3), Some((2, 3)));
+        assert_eq!(Topology::CrossSurface.resolve(0, -1, 4, 3), Some((2, 3)));
+        assert_eq!(Topology::CrossSurface.resolve(1, 4, 4, 3), Some((1, 0)));
+        assert_eq!(Topology::CrossSurface.resolve(-1, -1, 4, 3), Some((0, 0)));
+        assert_eq!(Topology::CrossSurface.resolve(3, 4, 4, 3), Some((2, 3)));
+    }
+
+    #[test]
+    fn test_resolve_row() {
+        assert_eq!(Topology::Torus.resolve_row(-1, 3), Some((2, false)));
+        assert_eq!(Topology::Bounded.resolve_row(3, 3), None);
+        assert_eq!(Topology::Mirror.resolve_row(3, 3), Some((2, false)));
+        assert_eq!(Topology::KleinBottle.resolve_row(3, 3), Some((0, true)));
+        assert_eq!(Topology::CrossSurface.resolve_row(-1, 3), Some((2, true)));
+    }
+}
@@ -1,65 +1,313 @@
-use std::fmt;
+use std::{error::Error, fmt};
 
 use wasm_bindgen::prelude::*;
 
-use crate::{cells::Cell, utils::{hades, set_panic_hook}};
+use crate::{
+    cells::Cell, history::{Change, Diff, History}, neighbourhood::Neighbourhood, packed::PackedGrid, random::{check_density, Rng, SoupError, Symmetry},
+    rules::{Rule, RuleError}, summed::SummedArea, timeline::{SeekError, Timeline}, topology::Topology, utils::set_panic_hook,
+};
 
+/** the most cells a universe may have, 8192x8192 of them */
+pub const MAX_CELLS: usize = 1 << 26;
+
+/** the representation used to compute the next epoch */
 #[wasm_bindgen]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Backend {
+    /** count the neightbours of every cell one by one, or with a summed-area table for larger than life neighbourhoods */
+    #[default]
+    Dense = 0,
+    /** pack 64 cells into a word and step whole words with bitwise arithmetic, life-like rules only */
+    Packed = 1,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EditError {
+    OutOfBounds { row: u32, col: u32 },
+    /** a flat coordinate list has to hold `row, col` pairs */
+    UnpairedCoordinates(usize),
+    /** the state is not below the number of states of the rule */
+    InvalidState { state: u8, states: u16 },
+}
+
+/** the size of a universe which does not have between 1 and `MAX_CELLS` cells, see `cell_count` */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SizeError {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[wasm_bindgen]
+#[derive(Clone)]
 pub struct Universe {
     width: u32,
     height: u32,
     // a one-dimension vec that stored a flatterned grid (i.e. |..row1..|..r2..|..r3..| )
-    cells: Vec<Cell>
+    cells: Vec<Cell>,
+    rule: Rule,
+    /** the `(row, col, weight)` offsets of the neightbours in the rule's neighbourhood, counted for every cell */
+    neightbours: Vec<(i64, i64, i32)>,
+    topology: Topology,
+    backend: Backend,
+    /** the packed backend's grid, kept from one generation to the next until the cells are changed some other way */
+    packed: Option<PackedGrid>,
+    generation: u64,
+    history: History,
+    timeline: Timeline,
+    /** the indices of the cells which came to life in the last change */
+    born: Vec<u32>,
+    /** the indices of the cells which died or started dying in the last change */
+    died: Vec<u32>,
 }
 
 impl Universe {
     pub fn init_cells(&mut self, initial_cells: Vec<[u32; 2]>) {
-        let mut cells = self.cells.clone();
-        for [row, col] in initial_cells {
-            let idx = self.to_index(row, col);
-            cells[idx] = Cell::Alive;
-        }
+        let indices: Vec<usize> = initial_cells.into_iter().map(|[row, col]| self.to_index(row, col)).collect();
+        self.edit(indices, |_| Cell::Alive);
+    }
 
-        self.cells = cells;
+    /** like `init_cells`, but giving each cell its own state, nothing is changed if any state is invalid */
+    pub fn init_states(&mut self, initial_cells: Vec<([u32; 2], Cell)>) -> Result<(), EditError> {
+        for (_, cell) in &initial_cells {
+            self.check_state(*cell)?;
+        }
+        let cells: Vec<(usize, Cell)> = initial_cells.into_iter()
+            .map(|([row, col], cell)| (self.to_index(row, col), cell))
+            .collect();
+        self.edit_states(cells);
+        Ok(())
     }
 
     pub fn next_epoch(&mut self) {
-        let cells = self.cells.clone();
-        let mut next_cells = self.cells.clone();
-        for (index, cell) in cells.into_iter().enumerate() {
-            let (row, col) = self.from_index(index);
-            let living_neightbour = self.living_neightbour_count(row, col);
-            let next_epoch_state = hades(cell, living_neightbour);
-            next_cells[index] = next_epoch_state;
+        match self.backend {
+            // the bitwise arithmetic only knows two states and the moore neighbourhood
+            Backend::Packed if self.rule.is_life_like() => self.next_epoch_packed(),
+            // counting cell by cell takes a time quadratic in the range, the summed-area table doesn't
+            _ if self.rule.neighbourhood().range() > 1 && SummedArea::supports(self.rule.neighbourhood()) => self.next_epoch_summed(),
+            _ => self.next_epoch_dense(),
+        }
+    }
+
+    fn next_epoch_dense(&mut self) {
+        let next_cells: Vec<Cell> = (0..self.cells.len())
+            .map(|index| {
+                let (row, col) = self.from_index(index);
+                let living_neightbour = self.living_neightbour_count(row, col);
+                self.rule.next_state(self.cells[index], living_neightbour)
+            })
+            .collect();
+
+        self.replace_cells(next_cells, 1);
+    }
+
+    fn next_epoch_summed(&mut self) {
+        let neighbourhood = self.rule.neighbourhood();
+        let summed = SummedArea::new(&self.cells, self.width, self.height, neighbourhood, self.topology);
+        let next_cells: Vec<Cell> = (0..self.cells.len())
+            .map(|index| {
+                let (row, col) = self.from_index(index);
+                let living_neightbour = summed.count(neighbourhood, row, col, self.cells[index]);
+                self.rule.next_state(self.cells[index], living_neightbour)
+            })
+            .collect();
+
+        self.replace_cells(next_cells, 1);
+    }
+
+    /**
+     * step the packed grid, and only unpack the cells that changed into `cells`.
+     * the first step after the cells were changed otherwise packs them again and unpacks them all,
+     * which also takes care of the dying states a generations rule may have left behind
+     */
+    fn next_epoch_packed(&mut self) {
+        let Some(packed) = self.packed.take() else {
+            let next = PackedGrid::from_cells(&self.cells, self.width, self.height).step(&self.rule, self.topology);
+            let mut next_cells = self.cells.clone();
+            next.write_cells(&mut next_cells);
+            self.replace_cells(next_cells, 1);
+            self.packed = Some(next);
+            return;
+        };
+
+        let next = packed.step(&self.rule, self.topology);
+        let changes = packed.changed(&next)
+            .map(|index| {
+                let after = if self.cells[index].is_alive() { Cell::Dead } else { Cell::Alive };
+                Change { index: index as u32, before: self.cells[index], after }
+            })
+            .collect();
+        let diff = Diff { changes, generations: 1 };
+        diff.apply(&mut self.cells);
+        self.commit(diff);
+        self.packed = Some(next);
+    }
+
+    /**
+     * change the cells at `indices` one after the other to `state(current state)`.
+     * every edit goes through here or `replace_cells` so it can be undone
+     */
+    fn edit(&mut self, indices: impl IntoIterator<Item = usize>, mut state: impl FnMut(Cell) -> Cell) {
+        self.packed = None;
+        let mut diff = Diff::default();
+        for index in indices {
+            let after = state(self.cells[index]);
+            self.change(&mut diff, index, after);
         }
+        self.commit(diff);
+    }
 
+    /** like `edit`, but with the new state of each index given up front */
+    fn edit_states(&mut self, cells: impl IntoIterator<Item = (usize, Cell)>) {
+        self.packed = None;
+        let mut diff = Diff::default();
+        for (index, after) in cells {
+            self.change(&mut diff, index, after);
+        }
+        self.commit(diff);
+    }
+
+    fn change(&mut self, diff: &mut Diff, index: usize, after: Cell) {
+        let before = self.cells[index];
+        if before != after {
+            self.cells[index] = after;
+            diff.changes.push(Change { index: index as u32, before, after });
+        }
+    }
+
+    /** swap in the state `generations` generations ahead (0 for an edit), recording the cells that changed */
+    fn replace_cells(&mut self, next_cells: Vec<Cell>, generations: i64) {
+        self.packed = None;
+        let mut diff = Diff::between(&self.cells, &next_cells);
+        diff.generations = generations;
         self.cells = next_cells;
+        self.commit(diff);
+    }
+
+    /** bring the generation counter and the timeline up to date with a diff that was just applied */
+    fn commit(&mut self, diff: Diff) {
+        self.note_changes(&diff, false);
+        if diff.is_empty() {
+            return;
+        }
+        let before = self.generation;
+        self.generation = shifted(before, diff.generations);
+        match diff.generations {
+            0 => self.timeline.edit(self.generation, &self.cells),
+            1 => self.timeline.step(before, &diff, &self.cells),
+            _ => {}
+        }
+        self.history.record(diff);
+    }
+
+    /** remember which cells were born and which died, for repainting only those */
+    fn note_changes(&mut self, diff: &Diff, reverted: bool) {
+        self.born.clear();
+        self.died.clear();
+        for change in &diff.changes {
+            let now = if reverted { change.before } else { change.after };
+            if now.is_alive() {
+                self.born.push(change.index);
+            } else {
+                self.died.push(change.index);
+            }
+        }
+    }
+
+    /** a copy of the universe which does not record any history, for running it ahead */
+    pub(crate) fn detached(&self) -> Universe {
+        Universe {
+            cells: self.cells.clone(),
+            history: History::with_budget(0),
+            timeline: Timeline::disabled(),
+            born: vec![],
+            died: vec![],
+            rule: self.rule.clone(),
+            neightbours: self.neightbours.clone(),
+            packed: self.packed.clone(),
+            ..*self
+        }
+    }
+
+    pub fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    pub fn replace_rule(&mut self, rule: Rule) {
+        self.neightbours = rule.neighbourhood().cells();
+        self.rule = rule;
+    }
+
+    /** the living cells as `[row, col]` coordinates in row-major order */
+    pub fn living_cells(&self) -> Vec<[u32; 2]> {
+        self.cells.iter().enumerate()
+            .filter(|(_, cell)| **cell == Cell::Alive)
+            .map(|(idx, _)| {
+                let (row, col) = self.from_index(idx);
+                [row, col]
+            })
+            .collect()
+    }
+
+    pub(crate) fn cell_slice(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    /** the indices of the cells born and the cells died in the last change */
+    pub(crate) fn changes(&self) -> (&[u32], &[u32]) {
+        (&self.born, &self.died)
     }
 
     pub fn cells_to_arr(&self) -> Vec<u8> {
-        self.cells.clone().into_iter().map(|v| v as u8).collect()
+        self.cells.iter().map(|cell| cell.state()).collect()
     }
 
-    fn living_neightbour_count(&self, row: u32, col: u32) -> u8 {
-        let mut counts = 0u8;
-        for row_delta in [self.height - 1, self.height, self.height + 1] {
-            let r = (row + row_delta) as u32 % self.height;
-            for col_delta in [self.width - 1, self.width, self.width + 1] {
-                let c = (col + col_delta) as u32 % self.width;
-                if r == row && c == col { continue }
+    /** the weighted sum of the living neightbours, which is their number for unweighted neighbourhoods */
+    fn living_neightbour_count(&self, row: u32, col: u32) -> i32 {
+        let mut counts = 0;
+        for (row_delta, col_delta, weight) in &self.neightbours {
+            let neightbour = self.topology.resolve(
+                row as i64 + row_delta,
+                col as i64 + col_delta,
+                self.width,
+                self.height,
+            );
+            if let Some((r, c)) = neightbour {
                 let idx = self.to_index(r, c);
-                println!("[{}, {}] {}", r, c, self.cells[idx]);
-                counts += self.cells[idx] as u8;
+                counts += self.cells[idx].is_alive() as i32 * weight;
             }
         }
 
         counts
     }
 
+    fn checked_index(&self, row: u32, col: u32) -> Result<usize, EditError> {
+        if row >= self.height || col >= self.width {
+            return Err(EditError::OutOfBounds { row, col });
+        }
+        Ok(self.to_index(row, col))
+    }
+
+    /** validate a flat `[row, col, row, col, ..]` list as a whole before anything is edited */
+    fn checked_indices(&self, coordinates: &[u32]) -> Result<Vec<usize>, EditError> {
+        if !coordinates.len().is_multiple_of(2) {
+            return Err(EditError::UnpairedCoordinates(coordinates.len()));
+        }
+        coordinates.chunks(2).map(|pair| self.checked_index(pair[0], pair[1])).collect()
+    }
+
+    fn check_state(&self, cell: Cell) -> Result<Cell, EditError> {
+        if !self.rule.has_state(cell) {
+            return Err(EditError::InvalidState { state: cell.state(), states: self.rule.states() });
+        }
+        Ok(cell)
+    }
+
     fn to_index(&self, row: u32, col: u32) -> usize {
         (self.width * row + col) as usize
     }
 
+    #[allow(clippy::wrong_self_convention)]
     fn from_index(&self, index: usize) -> (u32, u32) {
         let row = index / self.width as usize;
         let col = index % self.width as usize;
@@ -68,13 +316,87 @@ impl Universe {
     }
 }
 
-#[wasm_bindgen]
 impl Universe {
+    /** panics on sizes `try_new` rejects, for sizes known to be valid */
     pub fn new(width: u32, height: u32) -> Universe {
-        Universe {
+        Universe::with_topology(width, height, Topology::default())
+    }
+
+    /** panics on sizes `try_with_topology` rejects, for sizes known to be valid */
+    pub fn with_topology(width: u32, height: u32, topology: Topology) -> Universe {
+        Universe::try_with_topology(width, height, topology).unwrap_or_else(|err| panic!("{}", err))
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    #[wasm_bindgen(js_name = new)]
+    pub fn try_new(width: u32, height: u32) -> Result<Universe, SizeError> {
+        Universe::try_with_topology(width, height, Topology::default())
+    }
+
+    /** a universe of dead cells, unless it would not have between 1 and `MAX_CELLS` cells */
+    #[wasm_bindgen(js_name = with_topology)]
+    pub fn try_with_topology(width: u32, height: u32, topology: Topology) -> Result<Universe, SizeError> {
+        let count = cell_count(width, height).ok_or(SizeError { width, height })?;
+        let cells = vec![Cell::Dead; count];
+        Ok(Universe {
             width, height,
-            cells: vec![Cell::Dead; (width * height) as usize],
-        }
+            timeline: Timeline::disabled(),
+            cells,
+            neightbours: Rule::default().neighbourhood().cells(),
+            rule: Rule::default(),
+            topology,
+            backend: Backend::default(),
+            packed: None,
+            generation: 0,
+            history: History::default(),
+            born: vec![],
+            died: vec![],
+        })
+    }
+
+    /** change how the edges of the universe are glued together */
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    /** choose how `tick` computes the next epoch, every backend gives identical results */
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+        self.packed = None;
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /**
+     * replace the rule used by `tick` with the one described by a rulestring,
+     * e.g. `B36/S23` (highlife), `23/3` (conway in S/B notation) or `R2,C0,M0,S2..3,B3,NN` (range 2 von neumann)
+     */
+    pub fn set_rule(&mut self, rulestring: &str) -> Result<(), RuleError> {
+        self.replace_rule(rulestring.parse()?);
+        Ok(())
+    }
+
+    /**
+     * keep the birth and survival counts of the rule, but evaluate them over the weighted sum of the neightbours
+     * given by a square mask of `weights` row by row, see `Neighbourhood::weighted`
+     */
+    pub fn set_neighbourhood_weights(&mut self, weights: Vec<i8>) -> Result<(), RuleError> {
+        let neighbourhood = Neighbourhood::weighted(&weights)?;
+        self.replace_rule(self.rule.clone().with_neighbourhood(neighbourhood)?);
+        Ok(())
+    }
+
+    /** the current rule in `B../S..` notation, or golly's `R..,C..,M..,S..,B..,N..` for larger neighbourhoods */
+    pub fn rulestring(&self) -> String {
+        self.rule.to_string()
     }
 
     /**
@@ -85,6 +407,245 @@ impl Universe {
         self.init_cells(vec![[row, col]]);
     }
 
+    pub fn get_cell(&self, row: u32, col: u32) -> Result<Cell, EditError> {
+        Ok(self.cells[self.checked_index(row, col)?])
+    }
+
+    pub fn set_cell(&mut self, row: u32, col: u32, cell: Cell) -> Result<(), EditError> {
+        let cell = self.check_state(cell)?;
+        let index = self.checked_index(row, col)?;
+        self.edit([index], |_| cell);
+        Ok(())
+    }
+
+    /** flip a cell between dead and alive, returning its new state */
+    pub fn toggle_cell(&mut self, row: u32, col: u32) -> Result<Cell, EditError> {
+        let index = self.checked_index(row, col)?;
+        self.edit([index], toggled);
+        Ok(self.cells[index])
+    }
+
+    /** set every cell of a flat `[row, col, row, col, ..]` list, nothing is changed if any of them is invalid */
+    pub fn set_cells(&mut self, coordinates: &[u32], cell: Cell) -> Result<(), EditError> {
+        let cell = self.check_state(cell)?;
+        let indices = self.checked_indices(coordinates)?;
+        self.edit(indices, |_| cell);
+        Ok(())
+    }
+
+    /** toggle every cell of a flat `[row, col, row, col, ..]` list, nothing is changed if any of them is invalid */
+    pub fn toggle_cells(&mut self, coordinates: &[u32]) -> Result<(), EditError> {
+        let indices = self.checked_indices(coordinates)?;
+        self.edit(indices, toggled);
+        Ok(())
+    }
+
+    /** kill every cell */
+    pub fn clear(&mut self) {
+        self.edit(0..self.cells.len(), |_| Cell::Dead);
+    }
+
+    /** replace every cell with a random soup in which each cell is alive with probability `density` */
+    pub fn randomize(&mut self, seed: u64, density: f64) -> Result<(), SoupError> {
+        self.randomize_symmetric(seed, density, Symmetry::C1)
+    }
+
+    /** like `randomize`, but only the `width` x `height` region with its top left corner at `top`, `left` */
+    pub fn randomize_rect(
+        &mut self, seed: u64, density: f64, top: u32, left: u32, width: u32, height: u32,
+    ) -> Result<(), SoupError> {
+        check_density(density)?;
+        let fits = top as u64 + height as u64 <= self.height as u64
+            && left as u64 + width as u64 <= self.width as u64;
+        if !fits {
+            return Err(SoupError::OutOfBounds { top, left, width, height });
+        }
+
+        let mut rng = Rng::new(seed);
+        let indices: Vec<usize> = (top..top + height)
+            .flat_map(|row| (left..left + width).map(move |col| (row, col)))
+            .map(|(row, col)| self.to_index(row, col))
+            .collect();
+        self.edit(indices, |_| if rng.chance(density) { Cell::Alive } else { Cell::Dead });
+        Ok(())
+    }
+
+    /**
+     * replace every cell with a random soup which is invariant under `symmetry`.
+     * a random state is drawn for the first cell (in row-major order) of every orbit
+     * and copied to the rest of it
+     */
+    pub fn randomize_symmetric(&mut self, seed: u64, density: f64, symmetry: Symmetry) -> Result<(), SoupError> {
+        check_density(density)?;
+        symmetry.check(self.width, self.height)?;
+
+        let mut rng = Rng::new(seed);
+        let mut drawn = vec![false; self.cells.len()];
+        let mut next_cells = vec![Cell::Dead; self.cells.len()];
+        for idx in 0..self.cells.len() {
+            if drawn[idx] {
+                continue;
+            }
+            let cell = if rng.chance(density) { Cell::Alive } else { Cell::Dead };
+            let (row, col) = self.from_index(idx);
+            for (row, col) in symmetry.orbit(row, col, self.width, self.height) {
+                let idx = self.to_index(row, col);
+                drawn[idx] = true;
+                next_cells[idx] = cell;
+            }
+        }
+        self.replace_cells(next_cells, 0);
+        Ok(())
+    }
+
+    /** revert the last edit or generation step, returns whether there was anything to undo */
+    pub fn undo(&mut self) -> bool {
+        let Some(diff) = self.history.undo() else {
+            return false;
+        };
+        diff.revert(&mut self.cells);
+        let diff = diff.clone();
+        self.packed = None;
+        self.note_changes(&diff, true);
+        self.generation = shifted(self.generation, -diff.generations);
+        if diff.generations == 0 {
+            self.timeline.edit(self.generation, &self.cells);
+        }
+        true
+    }
+
+    /** apply the last undone edit or generation step again, returns whether there was anything to redo */
+    pub fn redo(&mut self) -> bool {
+        let Some(diff) = self.history.redo() else {
+            return false;
+        };
+        let (before, diff) = (self.generation, diff.clone());
+        diff.apply(&mut self.cells);
+        self.packed = None;
+        self.note_changes(&diff, false);
+        self.generation = shifted(before, diff.generations);
+        match diff.generations {
+            0 => self.timeline.edit(self.generation, &self.cells),
+            1 => self.timeline.step(before, &diff, &self.cells),
+            _ => {}
+        }
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    /**
+     * limit the memory used to remember edits and generation steps, in bytes.
+     * the oldest ones are forgotten first. a new universe starts with `history::DEFAULT_BUDGET`,
+     * a budget of 0 turns the history off
+     */
+    pub fn set_history_budget(&mut self, bytes: usize) {
+        self.history.set_budget(bytes);
+    }
+
+    pub fn history_budget(&self) -> usize {
+        self.history.budget()
+    }
+
+    /** forget every edit and generation step recorded so far */
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    /** how many generations the universe has been stepped, less the ones undone or rewound */
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /**
+     * go back (or forward again) to any generation recorded by the timeline.
+     * the recorded future is kept until the universe is edited or stepped differently,
+     * and the jump itself can be undone
+     */
+    pub fn seek(&mut self, generation: u64) -> Result<(), SeekError> {
+        if generation == self.generation {
+            return Ok(());
+        }
+        let cells = self.timeline.reconstruct(generation)?;
+        let mut diff = Diff::between(&self.cells, &cells);
+        diff.generations = distance(self.generation, generation);
+        self.cells = cells;
+        self.packed = None;
+        self.generation = generation;
+        self.note_changes(&diff, false);
+        self.history.record(diff);
+        Ok(())
+    }
+
+    /** the earliest generation `seek` can go back to */
+    pub fn earliest_generation(&self) -> Option<u64> {
+        self.timeline.range().map(|(earliest, _)| earliest)
+    }
+
+    /** the latest generation `seek` can go forward to */
+    pub fn latest_generation(&self) -> Option<u64> {
+        self.timeline.range().map(|(_, latest)| latest)
+    }
+
+    /** take a full snapshot of the cells every `interval` generations, the ones between are stored as diffs */
+    pub fn set_keyframe_interval(&mut self, interval: u32) {
+        self.timeline.set_interval(interval);
+    }
+
+    pub fn keyframe_interval(&self) -> u32 {
+        self.timeline.interval()
+    }
+
+    /**
+     * limit the memory used by the timeline, in bytes.
+     * the oldest generations are forgotten first. the timeline is off (a budget of 0) until it is given a budget,
+     * `timeline::DEFAULT_BUDGET` is a reasonable one, and then records from the current generation on
+     */
+    pub fn set_timeline_budget(&mut self, bytes: usize) {
+        self.timeline.set_budget(bytes);
+        if self.timeline.range().is_none() {
+            self.timeline.edit(self.generation, &self.cells);
+        }
+    }
+
+    pub fn timeline_budget(&self) -> usize {
+        self.timeline.budget()
+    }
+
+    /** the indices of the cells that came to life in the last tick, edit, undo, redo or seek */
+    pub fn born(&self) -> Vec<u32> {
+        self.born.clone()
+    }
+
+    /** the indices of the cells that died in the last tick, edit, undo, redo or seek */
+    pub fn died(&self) -> Vec<u32> {
+        self.died.clone()
+    }
+
+    /** `born` without copying, read `born_len` indices from wasm memory */
+    pub fn born_ptr(&self) -> *const u32 {
+        self.born.as_ptr()
+    }
+
+    pub fn born_len(&self) -> usize {
+        self.born.len()
+    }
+
+    /** `died` without copying, read `died_len` indices from wasm memory */
+    pub fn died_ptr(&self) -> *const u32 {
+        self.died.as_ptr()
+    }
+
+    pub fn died_len(&self) -> usize {
+        self.died.len()
+    }
+
     pub fn render(&self) -> String {
         self.to_string()
     }
@@ -114,17 +675,75 @@ impl fmt::Display for Universe {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         set_panic_hook();
 
-        let chunks = self.cells.chunks(self.width as usize).into_iter().enumerate();
+        let chunks = self.cells.chunks(self.width as usize).enumerate();
         for (idx, chunk) in chunks {
             for cell in chunk {
-                write!(f, "{}", cell.to_string())?;
+                write!(f, "{}", cell)?;
             }
-            if idx < chunk.len() - 1 { write!(f, "\n")? };
+            if idx + 1 < self.height as usize { writeln!(f)? };
         }
         Ok(())
     }
 }
 
+/** the number of cells of a `width` x `height` universe, `None` if there are none or more than `MAX_CELLS` */
+pub fn cell_count(width: u32, height: u32) -> Option<usize> {
+    (width as usize).checked_mul(height as usize).filter(|count| (1..=MAX_CELLS).contains(count))
+}
+
+/** move the generation counter, which can neither go below 0 nor wrap around */
+fn shifted(generation: u64, generations: i64) -> u64 {
+    generation.checked_add_signed(generations).expect("the generation counter left the range of u64")
+}
+
+/** how many generations `to` is ahead of `from`, which the timeline never records far enough apart to overflow */
+fn distance(from: u64, to: u64) -> i64 {
+    to.wrapping_sub(from) as i64
+}
+
+fn toggled(cell: Cell) -> Cell {
+    match cell {
+        Cell::Dead => Cell::Alive,
+        _ => Cell::Dead,
+    }
+}
+
+impl fmt::Display for SizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a {}x{} universe does not have between 1 and {} cells", self.width, self.height, MAX_CELLS)
+    }
+}
+
+impl Error for SizeError {}
+
+impl From<SizeError> for JsValue {
+    fn from(err: SizeError) -> Self {
+        js_sys::Error::new(&err.to_string()).into()
+    }
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::OutOfBounds { row, col } => write!(f, "cell ({}, {}) is outside of the universe", row, col),
+            EditError::UnpairedCoordinates(length) => {
+                write!(f, "expected row, col pairs but got {} coordinates", length)
+            }
+            EditError::InvalidState { state, states } => {
+                write!(f, "state {} is not one of the {} states of the rule", state, states)
+            }
+        }
+    }
+}
+
+impl Error for EditError {}
+
+impl From<EditError> for JsValue {
+    fn from(err: EditError) -> Self {
+        js_sys::Error::new(&err.to_string()).into()
+    }
+}
+
 #[wasm_bindgen]
 extern "C" {
     fn alert(s: &str);
@@ -132,7 +751,7 @@ extern "C" {
 
 #[cfg(test)]
 mod tests {
-    use crate::{cells::Cell, universe::Universe};
+    use crate::{cells::Cell, history, rules::{Rule, RuleError}, summed::SummedArea, topology::Topology, random::{SoupError, Symmetry}, timeline::{self, SeekError}, universe::{cell_count, Backend, EditError, SizeError, Universe, MAX_CELLS}};
 
     #[test]
     fn test_from_index() {
@@ -161,6 +780,13 @@ mod tests {
         assert_eq!(cells, [1,0,0,1]);
     }
 
+    #[test]
+    fn test_living_cells() {
+        let mut universe = Universe::new(3, 3);
+        universe.init_cells(vec![[2, 0], [0, 1]]);
+        assert_eq!(universe.living_cells(), vec![[0, 1], [2, 0]]);
+    }
+
     #[test]
     fn test_living_neightbour_count() {
         let mut universe = Universe::new(4,4);
@@ -181,6 +807,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::to_string_in_format_args)]
     fn test_display() {
         let mut universe = Universe::new(2,2);
         assert_eq!(universe.to_string(), format!(
@@ -205,6 +832,30 @@ mod tests {
         assert_eq!(universe.to_string(), expected_output);
     }
 
+    #[test]
+    fn test_cell_count() {
+        assert_eq!(cell_count(3, 2), Some(6));
+        assert_eq!(cell_count(0, 5), None);
+        assert_eq!(cell_count(8192, 8192), Some(MAX_CELLS));
+        assert_eq!(cell_count(8193, 8192), None);
+        assert_eq!(cell_count(u32::MAX, u32::MAX), None);
+
+        assert_eq!(Universe::try_new(0, 5).err(), Some(SizeError { width: 0, height: 5 }));
+        assert_eq!(Universe::try_with_topology(8193, 8192, Topology::Bounded).err(), Some(SizeError { width: 8193, height: 8192 }));
+        assert_eq!(Universe::try_new(3, 2).unwrap().cells_to_arr().len(), 6);
+    }
+
+    #[test]
+    fn test_display_not_square() {
+        let mut universe = Universe::new(2, 3);
+        universe.init_cells(vec![[2, 1]]);
+        let (alive, dead) = (Cell::Alive, Cell::Dead);
+        assert_eq!(universe.to_string(), format!("{dead}{dead}\n{dead}{dead}\n{dead}{alive}"));
+
+        let wide = Universe::new(3, 2);
+        assert_eq!(wide.to_string(), format!("{dead}{dead}{dead}\n{dead}{dead}{dead}"));
+    }
+
     #[test]
     fn test_next_epoch() {
         let mut universe = Universe::new(10,10);
@@ -238,4 +889,587 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_set_rule() {
+        let mut universe = Universe::new(5, 5);
+        assert_eq!(universe.rule(), &Rule::conway());
+        assert_eq!(universe.set_rule("B9/S23"), Err(RuleError::InvalidDigit('9')));
+        assert_eq!(universe.rule(), &Rule::conway());
+
+        // seeds: every cell dies, dead cells with exactly 2 neightbours are born
+        universe.set_rule("B2/S").unwrap();
+        assert_eq!(universe.rulestring(), "B2/S");
+        universe.init_cells(vec![[1, 1], [1, 2]]);
+        universe.next_epoch();
+
+        let mut expected = Universe::new(5, 5);
+        expected.init_cells(vec![[0, 1], [0, 2], [2, 1], [2, 2]]);
+        assert_eq!(universe.cells_to_arr(), expected.cells_to_arr());
+    }
+
+    const GLIDER: [[u32; 2]; 5] = [[0, 1], [1, 2], [2, 0], [2, 1], [2, 2]];
+
+    /**
+     * build the torus a twisted/reflected universe unfolds into,
+     * `tiles` tells for each quadrant whether rows and/or columns are mirrored
+     */
+    fn unfold(universe: &Universe, tiles: [[(bool, bool); 2]; 2]) -> Universe {
+        let (width, height) = (universe.width, universe.height);
+        let mut torus = Universe::new(width * tiles[0].len() as u32, height * tiles.len() as u32);
+        let mut living = vec![];
+        for (tile_row, row_tiles) in tiles.iter().enumerate() {
+            for (tile_col, (flip_rows, flip_cols)) in row_tiles.iter().enumerate() {
+                for (index, cell) in universe.cells.iter().enumerate() {
+                    if *cell == Cell::Dead { continue }
+                    let (row, col) = universe.from_index(index);
+                    let row = if *flip_rows { height - 1 - row } else { row };
+                    let col = if *flip_cols { width - 1 - col } else { col };
+                    living.push([tile_row as u32 * height + row, tile_col as u32 * width + col]);
+                }
+            }
+        }
+        torus.init_cells(living);
+        torus
+    }
+
+    /** run a glider across the edges and compare with the same glider on the unfolded torus */
+    fn assert_unfolds(topology: Topology, tiles: [[(bool, bool); 2]; 2]) {
+        let mut universe = Universe::with_topology(8, 6, topology);
+        universe.init_cells(GLIDER.to_vec());
+        let mut torus = unfold(&universe, tiles);
+
+        for _ in 0..60 {
+            universe.next_epoch();
+            torus.next_epoch();
+            assert_eq!(unfold(&universe, tiles).cells, torus.cells);
+        }
+    }
+
+    #[test]
+    fn test_torus_glider() {
+        let mut universe = Universe::with_topology(8, 8, Topology::Torus);
+        universe.init_cells(GLIDER.to_vec());
+        let initial = universe.cells_to_arr();
+        // a glider travels one cell diagonally every 4 generations
+        for _ in 0..4 * 8 {
+            universe.next_epoch();
+        }
+        assert_eq!(universe.cells_to_arr(), initial);
+    }
+
+    #[test]
+    fn test_bounded_glider() {
+        let mut universe = Universe::with_topology(8, 8, Topology::Bounded);
+        universe.init_cells(GLIDER.to_vec());
+        for _ in 0..40 {
+            universe.next_epoch();
+        }
+        // the glider crashes into the corner and leaves a block behind instead of wrapping
+        let mut block = Universe::new(8, 8);
+        block.init_cells(vec![[6, 6], [6, 7], [7, 6], [7, 7]]);
+        assert_eq!(universe.cells_to_arr(), block.cells_to_arr());
+    }
+
+    #[test]
+    fn test_mirror_glider() {
+        assert_unfolds(Topology::Mirror, [
+            [(false, false), (false, true)],
+            [(true, false), (true, true)],
+        ]);
+    }
+
+    #[test]
+    fn test_klein_bottle_glider() {
+        assert_unfolds(Topology::KleinBottle, [
+            [(false, false), (false, false)],
+            [(false, true), (false, true)],
+        ]);
+    }
+
+    #[test]
+    fn test_cross_surface_glider() {
+        assert_unfolds(Topology::CrossSurface, [
+            [(false, false), (true, false)],
+            [(false, true), (true, true)],
+        ]);
+    }
+
+    #[test]
+    fn test_set_topology() {
+        let mut universe = Universe::new(3, 3);
+        assert_eq!(universe.topology(), Topology::Torus);
+        universe.init_cells(vec![[0, 0]]);
+        assert_eq!(universe.living_neightbour_count(2, 2), 1);
+
+        universe.set_topology(Topology::Bounded);
+        assert_eq!(universe.topology(), Topology::Bounded);
+        assert_eq!(universe.living_neightbour_count(2, 2), 0);
+    }
+
+    #[test]
+    fn test_single_cell_edits() {
+        let mut universe = Universe::new(3, 2);
+        universe.set_cell(1, 2, Cell::Alive).unwrap();
+        assert_eq!(universe.get_cell(1, 2), Ok(Cell::Alive));
+        universe.set_cell(1, 2, Cell::Dead).unwrap();
+        assert_eq!(universe.get_cell(1, 2), Ok(Cell::Dead));
+
+        assert_eq!(universe.toggle_cell(0, 0), Ok(Cell::Alive));
+        assert_eq!(universe.toggle_cell(0, 0), Ok(Cell::Dead));
+
+        assert_eq!(universe.set_cell(2, 0, Cell::Alive), Err(EditError::OutOfBounds { row: 2, col: 0 }));
+        assert_eq!(universe.toggle_cell(0, 3), Err(EditError::OutOfBounds { row: 0, col: 3 }));
+        assert_eq!(universe.get_cell(5, 5), Err(EditError::OutOfBounds { row: 5, col: 5 }));
+        assert_eq!(universe.set_cell(0, 0, Cell::new(2)), Err(EditError::InvalidState { state: 2, states: 2 }));
+    }
+
+    #[test]
+    fn test_batch_edits() {
+        let mut universe = Universe::new(3, 3);
+        universe.set_cells(&[0, 0, 1, 1, 2, 2], Cell::Alive).unwrap();
+        assert_eq!(universe.cells_to_arr(), [1, 0, 0, 0, 1, 0, 0, 0, 1]);
+
+        universe.toggle_cells(&[0, 0, 0, 1]).unwrap();
+        assert_eq!(universe.cells_to_arr(), [0, 1, 0, 0, 1, 0, 0, 0, 1]);
+
+        // invalid lists are rejected as a whole
+        assert_eq!(universe.set_cells(&[0, 0, 1], Cell::Alive), Err(EditError::UnpairedCoordinates(3)));
+        assert_eq!(universe.toggle_cells(&[2, 0, 3, 0]), Err(EditError::OutOfBounds { row: 3, col: 0 }));
+        assert_eq!(universe.set_cells(&[0, 0], Cell::new(7)), Err(EditError::InvalidState { state: 7, states: 2 }));
+        assert_eq!(universe.cells_to_arr(), [0, 1, 0, 0, 1, 0, 0, 0, 1]);
+
+        universe.clear();
+        assert_eq!(universe.cells_to_arr(), [0; 9]);
+    }
+
+    #[test]
+    fn test_randomize() {
+        let mut universe = Universe::new(64, 64);
+        universe.randomize(7, 0.3).unwrap();
+        let mut same_seed = Universe::new(64, 64);
+        same_seed.randomize(7, 0.3).unwrap();
+        assert_eq!(universe.cells_to_arr(), same_seed.cells_to_arr());
+
+        let population = universe.cells_to_arr().iter().filter(|cell| **cell == 1).count();
+        assert!((1000..1450).contains(&population), "population {}", population);
+
+        let mut other_seed = Universe::new(64, 64);
+        other_seed.randomize(8, 0.3).unwrap();
+        assert_ne!(universe.cells_to_arr(), other_seed.cells_to_arr());
+
+        universe.randomize(7, 0.0).unwrap();
+        assert!(universe.cells_to_arr().iter().all(|cell| *cell == 0));
+        universe.randomize(7, 1.0).unwrap();
+        assert!(universe.cells_to_arr().iter().all(|cell| *cell == 1));
+
+        assert_eq!(universe.randomize(7, 1.5), Err(SoupError::InvalidDensity(1.5)));
+        assert!(matches!(universe.randomize(7, f64::NAN), Err(SoupError::InvalidDensity(_))));
+    }
+
+    #[test]
+    fn test_randomize_rect() {
+        let mut universe = Universe::new(6, 5);
+        universe.randomize_rect(1, 1.0, 1, 2, 3, 2).unwrap();
+        let living: Vec<usize> = universe.cells_to_arr().iter().enumerate()
+            .filter(|(_, cell)| **cell == 1)
+            .map(|(idx, _)| idx)
+            .collect();
+        assert_eq!(living, vec![8, 9, 10, 14, 15, 16]);
+
+        assert_eq!(
+            universe.randomize_rect(1, 0.5, 4, 0, 6, 2),
+            Err(SoupError::OutOfBounds { top: 4, left: 0, width: 6, height: 2 })
+        );
+
+        // a region covering the whole universe draws the same soup as `randomize`
+        let mut whole = Universe::new(6, 5);
+        whole.randomize(3, 0.5).unwrap();
+        universe.randomize_rect(3, 0.5, 0, 0, 6, 5).unwrap();
+        assert_eq!(universe.cells_to_arr(), whole.cells_to_arr());
+    }
+
+    #[test]
+    fn test_randomize_symmetric() {
+        type Transform = fn(u32, u32, u32) -> (u32, u32);
+        let transforms: [(Symmetry, Transform); 4] = [
+            (Symmetry::C2, |row, col, last| (last - row, last - col)),
+            (Symmetry::C4, |row, col, last| (col, last - row)),
+            (Symmetry::D2, |row, col, last| (row, last - col)),
+            (Symmetry::D8, |row, col, _| (col, row)),
+        ];
+        for (symmetry, transform) in transforms {
+            let mut universe = Universe::new(9, 9);
+            universe.randomize_symmetric(11, 0.5, symmetry).unwrap();
+            let cells = universe.cells_to_arr();
+            assert!(cells.contains(&1), "{:?}", symmetry);
+            for row in 0..9 {
+                for col in 0..9 {
+                    let (image_row, image_col) = transform(row, col, 8);
+                    assert_eq!(cells[(row * 9 + col) as usize], cells[(image_row * 9 + image_col) as usize]);
+                }
+            }
+        }
+
+        let mut universe = Universe::new(4, 6);
+        universe.randomize_symmetric(11, 0.5, Symmetry::D4).unwrap();
+        assert_eq!(universe.randomize_symmetric(11, 0.5, Symmetry::D8), Err(SoupError::NotSquare(Symmetry::D8)));
+    }
+
+    /** a universe which remembers its edits and generations */
+    fn recording(width: u32, height: u32) -> Universe {
+        let mut universe = Universe::new(width, height);
+        universe.set_timeline_budget(timeline::DEFAULT_BUDGET);
+        universe
+    }
+
+    #[test]
+    fn test_default_recording() {
+        // edits can be undone from the start, the timeline is opt-in
+        let mut universe = Universe::new(8, 8);
+        universe.init_cells(GLIDER.to_vec());
+        assert!(universe.can_undo());
+        assert_eq!((universe.history_budget(), universe.timeline_budget()), (history::DEFAULT_BUDGET, 0));
+        assert_eq!(universe.earliest_generation(), None);
+
+        universe.set_history_budget(0);
+        universe.next_epoch();
+        assert!(!universe.can_undo());
+
+        // the timeline starts from wherever the universe is once it is turned on
+        universe.set_timeline_budget(1 << 10);
+        universe.next_epoch();
+        assert_eq!((universe.earliest_generation(), universe.latest_generation()), (Some(1), Some(2)));
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut universe = recording(5, 5);
+        assert!(!universe.can_undo() && !universe.undo());
+
+        universe.toggle_cells(&[1, 2, 2, 2, 3, 2]).unwrap();
+        universe.set_cell(0, 0, Cell::Alive).unwrap();
+        universe.next_epoch();
+        let stepped = universe.cells_to_arr();
+
+        assert!(universe.undo());
+        assert_eq!(universe.living_cells(), vec![[0, 0], [1, 2], [2, 2], [3, 2]]);
+        assert!(universe.undo());
+        assert!(universe.undo());
+        assert!(universe.living_cells().is_empty());
+        assert!(!universe.undo());
+
+        assert!(universe.redo() && universe.redo() && universe.redo());
+        assert_eq!(universe.cells_to_arr(), stepped);
+        assert!(!universe.can_redo());
+
+        // a new edit drops whatever could have been redone
+        universe.undo();
+        universe.clear();
+        assert!(!universe.can_redo());
+        assert!(universe.undo());
+        assert_eq!(universe.living_cells(), vec![[0, 0], [1, 2], [2, 2], [3, 2]]);
+    }
+
+    #[test]
+    fn test_undo_repeated_edits() {
+        // the same cell toggled twice in one batch is restored in the right order
+        let mut universe = recording(3, 3);
+        universe.toggle_cells(&[1, 1, 1, 1, 0, 0]).unwrap();
+        assert_eq!(universe.living_cells(), vec![[0, 0]]);
+        universe.undo();
+        assert!(universe.living_cells().is_empty());
+
+        // edits that change nothing are not recorded
+        universe.set_cell(2, 2, Cell::Dead).unwrap();
+        assert!(!universe.can_undo() && universe.can_redo());
+    }
+
+    #[test]
+    fn test_history_budget() {
+        let mut universe = Universe::new(8, 8);
+        universe.set_history_budget(0);
+        universe.toggle_cell(1, 1).unwrap();
+        assert!(!universe.can_undo());
+
+        universe.set_history_budget(1 << 10);
+        universe.randomize(1, 0.5).unwrap();
+        for _ in 0..100 {
+            universe.next_epoch();
+        }
+        assert!(universe.can_undo());
+        let mut undone = 0;
+        while universe.undo() {
+            undone += 1;
+        }
+        assert!(undone < 100, "{} steps fit into 1KiB", undone);
+
+        universe.clear_history();
+        assert!(!universe.can_undo() && !universe.can_redo());
+    }
+
+    #[test]
+    fn test_generation() {
+        let mut universe = recording(6, 6);
+        universe.init_cells(GLIDER.to_vec());
+        for _ in 0..5 {
+            universe.next_epoch();
+        }
+        assert_eq!(universe.generation(), 5);
+
+        // still lifes keep counting
+        let mut block = Universe::new(4, 4);
+        block.init_cells(vec![[1, 1], [1, 2], [2, 1], [2, 2]]);
+        block.tick();
+        assert_eq!(block.generation(), 1);
+
+        universe.undo();
+        universe.undo();
+        assert_eq!(universe.generation(), 3);
+        universe.toggle_cell(0, 0).unwrap();
+        assert_eq!(universe.generation(), 3);
+        universe.undo();
+        universe.redo();
+        universe.redo();
+        assert_eq!(universe.generation(), 3);
+    }
+
+    #[test]
+    fn test_seek() {
+        let mut universe = recording(12, 12);
+        universe.set_keyframe_interval(4);
+        universe.init_cells(GLIDER.to_vec());
+        let mut states = vec![universe.cells_to_arr()];
+        for _ in 0..30 {
+            universe.next_epoch();
+            states.push(universe.cells_to_arr());
+        }
+
+        for generation in [17, 0, 30, 3, 8] {
+            universe.seek(generation).unwrap();
+            assert_eq!(universe.generation(), generation);
+            assert_eq!(universe.cells_to_arr(), states[generation as usize]);
+        }
+        assert_eq!(universe.seek(31), Err(SeekError::NotRecorded { generation: 31, earliest: 0, latest: 30 }));
+
+        // the jump can be undone
+        universe.undo();
+        assert_eq!(universe.generation(), 3);
+        assert_eq!(universe.cells_to_arr(), states[3]);
+
+        // stepping along the recorded run keeps the future, editing drops it
+        universe.next_epoch();
+        assert_eq!(universe.latest_generation(), Some(30));
+        universe.toggle_cell(0, 0).unwrap();
+        assert_eq!(universe.latest_generation(), Some(4));
+        assert!(universe.seek(10).is_err());
+        universe.seek(2).unwrap();
+        assert_eq!(universe.cells_to_arr(), states[2]);
+    }
+
+    #[test]
+    fn test_seek_after_undoing_edit() {
+        let mut universe = recording(8, 8);
+        universe.init_cells(GLIDER.to_vec());
+        universe.next_epoch();
+        universe.next_epoch();
+        let original = universe.cells_to_arr();
+
+        universe.set_cell(7, 7, Cell::Alive).unwrap();
+        universe.next_epoch();
+        universe.undo();
+        universe.undo();
+        assert_eq!(universe.cells_to_arr(), original);
+        universe.next_epoch();
+        universe.seek(2).unwrap();
+        assert_eq!(universe.cells_to_arr(), original);
+    }
+
+    #[test]
+    fn test_timeline_budget() {
+        let mut universe = Universe::new(16, 16);
+        universe.randomize(3, 0.4).unwrap();
+        universe.set_keyframe_interval(8);
+        universe.set_timeline_budget(4 << 10);
+        for _ in 0..200 {
+            universe.next_epoch();
+        }
+        let earliest = universe.earliest_generation().unwrap();
+        assert!(earliest > 0);
+        assert_eq!(universe.latest_generation(), Some(200));
+        universe.seek(earliest).unwrap();
+        assert!(universe.seek(earliest - 1).is_err());
+
+        universe.set_timeline_budget(0);
+        assert_eq!(universe.earliest_generation(), None);
+    }
+
+    #[test]
+    fn test_changed_cells() {
+        let mut universe = recording(5, 5);
+        universe.init_cells(vec![[2, 1], [2, 2], [2, 3]]);
+        assert_eq!((universe.born(), universe.died()), (vec![11, 12, 13], vec![]));
+
+        universe.tick();
+        assert_eq!((universe.born(), universe.died()), (vec![7, 17], vec![11, 13]));
+        assert_eq!((universe.born_len(), universe.died_len()), (2, 2));
+
+        universe.undo();
+        assert_eq!((universe.born(), universe.died()), (vec![11, 13], vec![7, 17]));
+        universe.redo();
+        assert_eq!((universe.born(), universe.died()), (vec![7, 17], vec![11, 13]));
+
+        universe.toggle_cell(0, 0).unwrap();
+        assert_eq!((universe.born(), universe.died()), (vec![0], vec![]));
+        universe.set_cell(0, 0, Cell::Alive).unwrap();
+        assert_eq!((universe.born(), universe.died()), (vec![], vec![]));
+
+        universe.seek(0).unwrap();
+        assert_eq!((universe.born(), universe.died()), (vec![11, 13], vec![0, 7, 17]));
+    }
+
+    #[test]
+    fn test_packed_keeps_grid() {
+        let mut dense = recording(70, 9);
+        dense.init_cells(vec![[1, 2], [2, 3], [3, 1], [3, 2], [3, 3], [5, 66], [5, 67], [5, 68]]);
+        let mut packed = dense.clone();
+        packed.set_backend(Backend::Packed);
+
+        let steps: [fn(&mut Universe); 8] = [
+            Universe::tick,
+            Universe::tick,
+            // edits, undo and seek change the cells behind the grid's back
+            |universe| { universe.toggle_cell(0, 0).unwrap(); },
+            Universe::tick,
+            |universe| { universe.undo(); },
+            Universe::tick,
+            |universe| universe.seek(1).unwrap(),
+            Universe::tick,
+        ];
+        for step in steps {
+            step(&mut dense);
+            step(&mut packed);
+            assert_eq!(packed.cells_to_arr(), dense.cells_to_arr());
+            assert_eq!((packed.born(), packed.died()), (dense.born(), dense.died()));
+        }
+        assert!(packed.packed.is_some());
+    }
+
+    #[test]
+    fn test_generations_rule() {
+        let mut universe = recording(6, 6);
+        universe.set_rule("B2/S/C3").unwrap();
+        universe.init_cells(vec![[2, 1], [2, 2]]);
+        let mut packed = universe.clone();
+        packed.set_backend(Backend::Packed);
+
+        universe.tick();
+        for [row, col] in [[1, 1], [1, 2], [3, 1], [3, 2]] {
+            assert_eq!(universe.get_cell(row, col), Ok(Cell::Alive));
+        }
+        assert_eq!(universe.get_cell(2, 1), Ok(Cell::new(2)));
+        assert_eq!(universe.died(), vec![13, 14]);
+
+        universe.tick();
+        assert_eq!(universe.get_cell(2, 1), Ok(Cell::Dead));
+        assert_eq!(universe.get_cell(1, 1), Ok(Cell::new(2)));
+        assert_eq!(universe.toggle_cell(1, 1), Ok(Cell::Dead));
+
+        packed.tick();
+        packed.tick();
+        universe.undo();
+        assert_eq!(packed.cells_to_arr(), universe.cells_to_arr());
+    }
+
+    #[test]
+    fn test_hexagonal_rule() {
+        let mut universe = Universe::new(5, 5);
+        universe.set_rule("B2/S34H").unwrap();
+        universe.init_cells(vec![[1, 1], [1, 2], [1, 3], [2, 1], [2, 3], [3, 1], [3, 2], [3, 3]]);
+        // the top right and bottom left corners are not hexagonal neightbours
+        assert_eq!(universe.living_neightbour_count(2, 2), 6);
+
+        let mut universe = Universe::new(5, 5);
+        universe.set_rule("B2/S34H").unwrap();
+        universe.init_cells(vec![[1, 3], [3, 1]]);
+        let mut packed = universe.clone();
+        packed.set_backend(Backend::Packed);
+        universe.tick();
+        assert!(universe.living_cells().is_empty());
+
+        // the packed backend steps hexagonal rules cell by cell
+        packed.tick();
+        assert_eq!(packed.cells_to_arr(), universe.cells_to_arr());
+    }
+
+    #[test]
+    fn test_von_neumann_rule() {
+        let mut universe = Universe::new(7, 7);
+        universe.set_rule("B1/S4V").unwrap();
+        universe.init_cells(vec![[3, 3]]);
+        universe.tick();
+        assert_eq!(universe.living_cells(), vec![[2, 3], [3, 2], [3, 4], [4, 3]]);
+
+        let mut universe = Universe::new(7, 7);
+        universe.set_rule("R2,C0,M0,S,B1,NN").unwrap();
+        universe.init_cells(vec![[3, 3]]);
+        assert_eq!(universe.living_neightbour_count(1, 3), 1);
+        assert_eq!(universe.living_neightbour_count(2, 2), 1);
+        assert_eq!(universe.living_neightbour_count(1, 2), 0);
+        universe.tick();
+        assert_eq!(universe.living_cells().len(), 12);
+    }
+
+    #[test]
+    fn test_weighted_neighbourhood() {
+        let mut universe = Universe::new(5, 5);
+        universe.set_rule("B4/S").unwrap();
+        // orthogonal neightbours count twice, the cell itself takes one away
+        universe.set_neighbourhood_weights(vec![1, 2, 1, 2, -1, 2, 1, 2, 1]).unwrap();
+        assert_eq!(universe.rulestring(), "R1,C0,M0,S,B4,NW01020102ff02010201");
+        universe.init_cells(vec![[2, 1], [2, 3]]);
+        assert_eq!(universe.living_neightbour_count(2, 2), 4);
+        assert_eq!(universe.living_neightbour_count(2, 1), -1);
+        universe.tick();
+        assert_eq!(universe.living_cells(), vec![[2, 2]]);
+
+        assert_eq!(universe.set_neighbourhood_weights(vec![1; 4]), Err(RuleError::InvalidNeighbourhood("4 weights do not make an odd sized square".to_string())));
+        assert_eq!(universe.set_neighbourhood_weights(vec![0, 1, 0, 1, 0, 1, 0, 0, 0]), Err(RuleError::InvalidCount(4)));
+    }
+
+    #[test]
+    fn test_summed_area_matches_dense() {
+        for topology in [Topology::Torus, Topology::Bounded, Topology::Mirror, Topology::KleinBottle, Topology::CrossSurface] {
+            for rulestring in ["R3,C0,M0,S2..10,B5..8,NM", "R4,C3,M1,S10..30,B12..20,NM", "R3,C0,M1,S3..9,B4..7,NN"] {
+                let mut universe = Universe::with_topology(17, 11, topology);
+                universe.set_rule(rulestring).unwrap();
+                universe.randomize(7, 0.4).unwrap();
+                universe.tick();
+                let neighbourhood = universe.rule().neighbourhood().clone();
+                let summed = SummedArea::new(&universe.cells, 17, 11, &neighbourhood, topology);
+                for index in 0..universe.cells.len() {
+                    let (row, col) = universe.from_index(index);
+                    let count = summed.count(&neighbourhood, row, col, universe.cells[index]);
+                    assert_eq!(count, universe.living_neightbour_count(row, col), "{:?} {} at {}, {}", topology, rulestring, row, col);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_larger_than_life() {
+        // a bug in bosco's rule, a small spaceship on a range 5 neighbourhood
+        let mut universe = Universe::new(64, 64);
+        universe.set_rule("R5,C0,M1,S34..58,B34..45,NM").unwrap();
+        assert_eq!(universe.rulestring(), "R5,C0,M1,S34..58,B34..45,NM");
+        universe.load_rle_at("x = 10, y = 10\n2b6o$2b6o$b8o$b8o$10o$3o4b3o$3o4b3o$b2o4b2o$2b2o2b2o$3b4o!", 30, 27).unwrap();
+        let ship = universe.detect_spaceship(12).unwrap();
+        assert_eq!((ship.period, ship.dx, ship.dy), (6, 0, -5));
+
+        for _ in 0..6 {
+            universe.tick();
+        }
+        assert_eq!(universe.living_cells().len(), 62);
+        assert_eq!(universe.get_cell(25, 29), Ok(Cell::Alive));
+    }
 }
@@ -8,52 +8,3 @@ pub fn set_panic_hook() {
     #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
 }
-
-use crate::cells::Cell;
-
-/**
- * "Hades, the god of the underworld. Only he can determine one to be alive or dead"
- *
- * determine the state of a cell in the next epoch given its neightours states
- * 
- * the law of hades
- * 1. Any live cell with fewer than two live neighbours dies, as if caused by underpopulation.
- * 2. Any live cell with two or three live neighbours lives on to the next generation.
- * 3. Any live cell with more than three live neighbours dies, as if by overpopulation.
- * 4. Any dead cell with exactly three live neighbours becomes a live cell, as if by reproduction.
- */
-pub fn hades(is_alive: Cell, living_neightbours: u8) -> Cell {
-    match (is_alive, living_neightbours) {
-        (Cell::Alive, x) if x < 2 => Cell::Dead,
-        (Cell::Alive, 2) | (Cell::Alive, 3) => Cell::Alive,
-        (Cell::Alive, x) if x > 3 => Cell::Dead,
-        (Cell::Dead, x) if x == 3 => Cell::Alive,
-        (otherwise, _) => otherwise
-    }
-}
-
-#[cfg(test)]
-mod hades_test {
-    use crate::utils::hades;
-    use crate::cells::Cell;
-    #[test]
-    fn test_hades() {
-        assert_eq!(hades(Cell::Alive, 1), Cell::Dead);
-        assert_eq!(hades(Cell::Alive, 2), Cell::Alive);
-        assert_eq!(hades(Cell::Alive, 3), Cell::Alive);
-        assert_eq!(hades(Cell::Alive, 4), Cell::Dead);
-        assert_eq!(hades(Cell::Alive, 5), Cell::Dead);
-        assert_eq!(hades(Cell::Alive, 6), Cell::Dead);
-        assert_eq!(hades(Cell::Alive, 7), Cell::Dead);
-        assert_eq!(hades(Cell::Alive, 8), Cell::Dead);
-
-        assert_eq!(hades(Cell::Dead, 1), Cell::Dead);
-        assert_eq!(hades(Cell::Dead, 2), Cell::Dead);
-        assert_eq!(hades(Cell::Dead, 3), Cell::Alive);
-        assert_eq!(hades(Cell::Dead, 4), Cell::Dead);
-        assert_eq!(hades(Cell::Dead, 5), Cell::Dead);
-        assert_eq!(hades(Cell::Dead, 6), Cell::Dead);
-        assert_eq!(hades(Cell::Dead, 7), Cell::Dead);
-        assert_eq!(hades(Cell::Dead, 8), Cell::Dead);
-    }
-}
\ No newline at end of file
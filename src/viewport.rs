@@ -0,0 +1,362 @@
+use std::ops::Range;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{cells::Cell, render::Palette, universe::Universe};
+
+/** the closest the viewport can zoom in, 2^6 = 64 pixels per cell */
+pub const MAX_ZOOM: i32 = 6;
+
+/** the furthest the viewport can zoom out, one pixel per 2^10 x 2^10 cells */
+pub const MIN_ZOOM: i32 = -10;
+
+/**
+ * a window of `screen_width` x `screen_height` pixels onto a universe.
+ * at zoom level `z` a cell is `2^z` pixels wide, so below 0 every pixel covers a block
+ * of cells and is shaded by how many of them are alive.
+ * `left`, `top` is the (fractional) cell at the top left corner of the screen,
+ * everything outside the universe is drawn in the grid colour of the palette
+ */
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Viewport {
+    screen_width: u32,
+    screen_height: u32,
+    left: f64,
+    top: f64,
+    zoom: i32,
+    palette: Palette,
+    frame: Vec<u8>,
+    /** the `left`, `top`, `zoom` and `[screen_width, screen_height, width, height]` the frame was drawn with */
+    drawn: Option<(f64, f64, i32, [u32; 4])>,
+}
+
+impl Viewport {
+    pub fn palette(&self) -> Palette {
+        self.palette
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    pub fn frame(&self) -> &[u8] {
+        &self.frame
+    }
+
+    /** how many pixels wide a cell is at the current zoom level */
+    pub fn scale(&self) -> f64 {
+        2f64.powi(self.zoom)
+    }
+
+    /** the cells (possibly outside the universe) covered by each pixel along one axis */
+    fn spans(&self, origin: f64, pixels: u32) -> Vec<Range<i64>> {
+        let scale = self.scale();
+        (0..pixels)
+            .map(|pixel| {
+                let start = (origin + pixel as f64 / scale).floor() as i64;
+                let end = (origin + (pixel + 1) as f64 / scale).floor() as i64;
+                start..end.max(start + 1)
+            })
+            .collect()
+    }
+
+    fn view(&self, universe: &Universe) -> (f64, f64, i32, [u32; 4]) {
+        let sizes = [self.screen_width, self.screen_height, universe.width(), universe.height()];
+        (self.left, self.top, self.zoom, sizes)
+    }
+
+    /** the colour of a pixel covering the cells `rows` x `cols` */
+    fn pixel(&self, universe: &Universe, rows: &Range<i64>, cols: &Range<i64>) -> [u8; 4] {
+        let (rows, cols) = (clip(rows, universe.height()), clip(cols, universe.width()));
+        let cells = universe.cell_slice();
+        let width = universe.width() as usize;
+        let total = (rows.len() * cols.len()) as u64;
+        let alive = rows
+            .flat_map(|row| &cells[row * width + cols.start..row * width + cols.end])
+            .filter(|cell| **cell == Cell::Alive)
+            .count() as u64;
+        self.shade(alive, total)
+    }
+
+    fn shade(&self, alive: u64, total: u64) -> [u8; 4] {
+        if total == 0 {
+            return self.palette.grid;
+        }
+        let (dead, living) = (self.palette.dead, self.palette.alive);
+        let mut color = [0; 4];
+        for channel in 0..4 {
+            let (from, to) = (dead[channel] as u64, living[channel] as u64);
+            color[channel] = ((from * (total - alive) + to * alive + total / 2) / total) as u8;
+        }
+        color
+    }
+}
+
+/** the pixels whose spans contain `cell`, spans being sorted */
+fn covering(spans: &[Range<i64>], cell: i64) -> Range<usize> {
+    spans.partition_point(|span| span.end <= cell)..spans.partition_point(|span| span.start <= cell)
+}
+
+/** the part of `span` inside `0..length` */
+fn clip(span: &Range<i64>, length: u32) -> Range<usize> {
+    let start = span.start.clamp(0, length as i64) as usize;
+    let end = span.end.clamp(0, length as i64) as usize;
+    start..end
+}
+
+#[wasm_bindgen]
+impl Viewport {
+    pub fn new(screen_width: u32, screen_height: u32) -> Viewport {
+        Viewport {
+            screen_width,
+            screen_height,
+            left: 0.0,
+            top: 0.0,
+            zoom: 0,
+            palette: Palette::default(),
+            frame: vec![],
+            drawn: None,
+        }
+    }
+
+    pub fn resize(&mut self, screen_width: u32, screen_height: u32) {
+        self.screen_width = screen_width;
+        self.screen_height = screen_height;
+    }
+
+    pub fn screen_width(&self) -> u32 {
+        self.screen_width
+    }
+
+    pub fn screen_height(&self) -> u32 {
+        self.screen_height
+    }
+
+    pub fn zoom(&self) -> i32 {
+        self.zoom
+    }
+
+    /** change the zoom level, keeping the cell at the centre of the screen in place */
+    pub fn set_zoom(&mut self, zoom: i32) {
+        self.zoom_at(self.screen_width as f64 / 2.0, self.screen_height as f64 / 2.0, zoom.saturating_sub(self.zoom));
+    }
+
+    /** zoom in (positive `levels`) or out, keeping the cell under the screen point `x`, `y` in place */
+    pub fn zoom_at(&mut self, x: f64, y: f64, levels: i32) {
+        let (col, row) = (self.left + x / self.scale(), self.top + y / self.scale());
+        self.zoom = self.zoom.saturating_add(levels).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.left = col - x / self.scale();
+        self.top = row - y / self.scale();
+    }
+
+    /** move the view by a number of screen pixels, as when dragging the picture by `dx`, `dy` */
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        self.left -= dx / self.scale();
+        self.top -= dy / self.scale();
+    }
+
+    /** put the centre of the screen on the given cell */
+    pub fn center_on(&mut self, row: f64, col: f64) {
+        self.left = col - self.screen_width as f64 / 2.0 / self.scale();
+        self.top = row - self.screen_height as f64 / 2.0 / self.scale();
+    }
+
+    /** zoom and pan so that the whole universe fits onto the screen */
+    pub fn fit(&mut self, universe: &Universe) {
+        let fits = |zoom: i32| {
+            let scale = 2f64.powi(zoom);
+            universe.width() as f64 * scale <= self.screen_width as f64
+                && universe.height() as f64 * scale <= self.screen_height as f64
+        };
+        self.zoom = (MIN_ZOOM..=MAX_ZOOM).rev().find(|zoom| fits(*zoom)).unwrap_or(MIN_ZOOM);
+        self.center_on(universe.height() as f64 / 2.0, universe.width() as f64 / 2.0);
+    }
+
+    pub fn left(&self) -> f64 {
+        self.left
+    }
+
+    pub fn top(&self) -> f64 {
+        self.top
+    }
+
+    /** the `[row, col]` of the cell under the screen point `x`, `y`, which may be outside the universe */
+    pub fn screen_to_cell(&self, x: f64, y: f64) -> Vec<i32> {
+        let row = (self.top + y / self.scale()).floor() as i32;
+        let col = (self.left + x / self.scale()).floor() as i32;
+        vec![row, col]
+    }
+
+    /** colours are given as `0xRRGGBBAA` */
+    pub fn set_alive_color(&mut self, rgba: u32) {
+        self.palette.alive = rgba.to_be_bytes();
+    }
+
+    pub fn set_dead_color(&mut self, rgba: u32) {
+        self.palette.dead = rgba.to_be_bytes();
+    }
+
+    pub fn set_background_color(&mut self, rgba: u32) {
+        self.palette.grid = rgba.to_be_bytes();
+    }
+
+    /** draw the visible part of the universe, reading only the cells on screen */
+    pub fn render(&mut self, universe: &Universe) {
+        let rows = self.spans(self.top, self.screen_height);
+        let cols = self.spans(self.left, self.screen_width);
+        let mut frame = Vec::with_capacity(self.screen_width as usize * self.screen_height as usize * 4);
+        for rows in &rows {
+            for cols in &cols {
+                frame.extend(self.pixel(universe, rows, cols));
+            }
+        }
+        self.frame = frame;
+        self.drawn = Some(self.view(universe));
+    }
+
+    /**
+     * only redraw the pixels showing cells born or died in the last change of the universe,
+     * or everything if the view was zoomed, panned or resized since the last frame
+     */
+    pub fn render_changes(&mut self, universe: &Universe) {
+        if self.drawn != Some(self.view(universe)) {
+            self.render(universe);
+            return;
+        }
+        let rows = self.spans(self.top, self.screen_height);
+        let cols = self.spans(self.left, self.screen_width);
+        let (born, died) = universe.changes();
+        let width = universe.width();
+        for index in born.iter().chain(died) {
+            let (row, col) = ((index / width) as i64, (index % width) as i64);
+            for y in covering(&rows, row) {
+                for x in covering(&cols, col) {
+                    let color = self.pixel(universe, &rows[y], &cols[x]);
+                    let start = (y * self.screen_width as usize + x) * 4;
+                    self.frame[start..start + 4].copy_from_slice(&color);
+                }
+            }
+        }
+    }
+
+    /** the RGBA pixels of the last frame, `frame_len` bytes from wasm memory */
+    pub fn frame_ptr(&self) -> *const u8 {
+        self.frame.as_ptr()
+    }
+
+    pub fn frame_len(&self) -> usize {
+        self.frame.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{render::Palette, universe::Universe, viewport::Viewport};
+
+    fn pixels(viewport: &Viewport) -> Vec<[u8; 4]> {
+        viewport.frame().chunks(4).map(|pixel| [pixel[0], pixel[1], pixel[2], pixel[3]]).collect()
+    }
+
+    #[test]
+    fn test_one_pixel_per_cell() {
+        let mut universe = Universe::new(3, 2);
+        universe.init_cells(vec![[0, 1], [1, 2]]);
+        let mut viewport = Viewport::new(3, 2);
+        viewport.render(&universe);
+        let Palette { alive, dead, .. } = Palette::default();
+        assert_eq!(pixels(&viewport), vec![dead, alive, dead, dead, dead, alive]);
+    }
+
+    #[test]
+    fn test_zoom_in_and_pan() {
+        let mut universe = Universe::new(3, 3);
+        universe.init_cells(vec![[0, 0]]);
+        let mut viewport = Viewport::new(4, 1);
+        viewport.set_zoom(1);
+        viewport.center_on(0.5, 1.0);
+        assert_eq!(viewport.screen_to_cell(0.0, 0.0), vec![0, 0]);
+        viewport.render(&universe);
+        let Palette { alive, dead, .. } = Palette::default();
+        assert_eq!(pixels(&viewport), vec![alive, alive, dead, dead]);
+
+        // dragging the picture to the right shows what is left of the universe
+        viewport.pan(2.0, 0.0);
+        viewport.render(&universe);
+        let Palette { alive, grid, .. } = Palette::default();
+        assert_eq!(pixels(&viewport), vec![grid, grid, alive, alive]);
+        assert_eq!(viewport.screen_to_cell(1.0, 0.0), vec![0, -1]);
+    }
+
+    #[test]
+    fn test_zoom_out_shades_by_density() {
+        let mut universe = Universe::new(4, 2);
+        universe.init_cells(vec![[0, 0], [1, 1], [0, 2], [0, 3], [1, 2], [1, 3]]);
+        let mut viewport = Viewport::new(2, 1);
+        viewport.set_zoom(-1);
+        viewport.center_on(1.0, 2.0);
+        viewport.render(&universe);
+        let Palette { alive, .. } = Palette::default();
+        assert_eq!(pixels(&viewport), vec![[0x80, 0x80, 0x80, 0xff], alive]);
+    }
+
+    #[test]
+    fn test_zoom_at_keeps_point() {
+        let mut viewport = Viewport::new(100, 80);
+        viewport.center_on(50.0, 50.0);
+        let before = viewport.screen_to_cell(30.0, 20.0);
+        viewport.zoom_at(30.0, 20.0, 3);
+        assert_eq!(viewport.zoom(), 3);
+        assert_eq!(viewport.screen_to_cell(30.0, 20.0), before);
+        viewport.zoom_at(30.0, 20.0, -100);
+        assert_eq!(viewport.zoom(), super::MIN_ZOOM);
+        assert_eq!(viewport.screen_to_cell(30.0, 20.0), before);
+    }
+
+    #[test]
+    fn test_render_changes() {
+        let mut universe = Universe::new(24, 16);
+        universe.randomize(5, 0.4).unwrap();
+        for zoom in [0, 2, -1, -3] {
+            let mut viewport = Viewport::new(30, 20);
+            viewport.set_zoom(zoom);
+            viewport.center_on(7.5, 11.0);
+            let mut incremental = viewport.clone();
+            incremental.render(&universe);
+            for _ in 0..5 {
+                universe.tick();
+                incremental.render_changes(&universe);
+                viewport.render(&universe);
+                assert_eq!(incremental.frame(), viewport.frame(), "zoom {}", zoom);
+            }
+
+            // moving the view redraws everything
+            viewport.pan(3.0, -2.0);
+            incremental.pan(3.0, -2.0);
+            universe.toggle_cell(3, 4).unwrap();
+            incremental.render_changes(&universe);
+            viewport.render(&universe);
+            assert_eq!(incremental.frame(), viewport.frame(), "zoom {}", zoom);
+        }
+    }
+
+    #[test]
+    fn test_zoom_saturates() {
+        let mut viewport = Viewport::new(10, 10);
+        viewport.zoom_at(0.0, 0.0, i32::MAX);
+        assert_eq!(viewport.zoom(), super::MAX_ZOOM);
+        viewport.set_zoom(i32::MIN);
+        assert_eq!(viewport.zoom(), super::MIN_ZOOM);
+    }
+
+    #[test]
+    fn test_fit() {
+        let universe = Universe::new(1000, 300);
+        let mut viewport = Viewport::new(400, 300);
+        viewport.fit(&universe);
+        assert_eq!(viewport.zoom(), -2);
+        assert_eq!(viewport.screen_to_cell(200.0, 150.0), vec![150, 500]);
+        viewport.render(&universe);
+        assert_eq!(viewport.frame_len(), 400 * 300 * 4);
+    }
+}
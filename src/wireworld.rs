@@ -0,0 +1,173 @@
+use crate::cells::Cell;
+
+/** the background, electrons never go there */
+pub const EMPTY: Cell = Cell::Dead;
+
+/** the front of an electron, the only state counted as a living neightbour */
+pub const HEAD: Cell = Cell::Alive;
+
+/** the back of an electron, which keeps it from turning around */
+pub const TAIL: Cell = Cell::new(2);
+
+/** a wire electrons travel along */
+pub const CONDUCTOR: Cell = Cell::new(3);
+
+/**
+ * the wireworld transition: heads become tails, tails become conductors again,
+ * and a conductor next to exactly one or two heads becomes a head
+ */
+pub fn next_state(cell: Cell, heads: i32) -> Cell {
+    match cell {
+        HEAD => TAIL,
+        TAIL => CONDUCTOR,
+        CONDUCTOR if heads == 1 || heads == 2 => HEAD,
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        formats::rle, rules::Rule, topology::Topology, universe::Universe,
+        wireworld::{next_state, CONDUCTOR, EMPTY, HEAD, TAIL},
+    };
+
+    /** a bounded wireworld universe drawn with `#` for conductors, `@` for heads and `~` for tails */
+    fn circuit(layout: &str) -> Universe {
+        let rows: Vec<&str> = layout.trim_matches('\n').lines().collect();
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0) as u32;
+        let mut universe = Universe::with_topology(width, rows.len() as u32, Topology::Bounded);
+        universe.replace_rule(Rule::wireworld());
+        for (row, line) in rows.iter().enumerate() {
+            for (col, character) in line.chars().enumerate() {
+                let cell = match character {
+                    '#' => CONDUCTOR,
+                    '@' => HEAD,
+                    '~' => TAIL,
+                    _ => EMPTY,
+                };
+                universe.set_cell(row as u32, col as u32, cell).unwrap();
+            }
+        }
+        universe
+    }
+
+    /** the ticks within the first `ticks` at which an electron head reaches `row`, `col` */
+    fn arrivals(universe: &mut Universe, row: u32, col: u32, ticks: u32) -> Vec<u32> {
+        (1..=ticks)
+            .filter(|_| {
+                universe.tick();
+                universe.get_cell(row, col) == Ok(HEAD)
+            })
+            .collect()
+    }
+
+    /** a two input gate, with an electron heading into the input `A` and `B` if they are set */
+    fn gate(layout: &str, a: bool, b: bool) -> Universe {
+        let input = |set: bool| if set { "~@" } else { "##" };
+        circuit(&layout.replace('A', input(a)).replace('B', input(b)))
+    }
+
+    #[test]
+    fn test_transition() {
+        assert_eq!(next_state(HEAD, 0), TAIL);
+        assert_eq!(next_state(TAIL, 3), CONDUCTOR);
+        assert_eq!(next_state(CONDUCTOR, 0), CONDUCTOR);
+        assert_eq!(next_state(CONDUCTOR, 1), HEAD);
+        assert_eq!(next_state(CONDUCTOR, 2), HEAD);
+        assert_eq!(next_state(CONDUCTOR, 3), CONDUCTOR);
+        assert_eq!(next_state(EMPTY, 2), EMPTY);
+        assert_eq!(Rule::wireworld().next_state(CONDUCTOR, 1), HEAD);
+        assert_eq!("wireworld".parse::<Rule>(), Ok(Rule::wireworld()));
+        assert_eq!(Rule::wireworld().to_string(), "WireWorld");
+    }
+
+    #[test]
+    fn test_wire() {
+        let mut universe = circuit("~@######");
+        assert_eq!(arrivals(&mut universe, 0, 7, 10), vec![6]);
+        assert_eq!(universe.get_cell(0, 0), Ok(CONDUCTOR));
+    }
+
+    #[test]
+    fn test_diode() {
+        let mut forward = circuit("
+    ##
+~@### #####
+    ##
+");
+        assert_eq!(arrivals(&mut forward, 1, 10, 30), vec![9]);
+
+        let mut backward = circuit("
+    ##
+##### ###@~
+    ##
+");
+        assert_eq!(arrivals(&mut backward, 1, 0, 30), vec![]);
+    }
+
+    #[test]
+    fn test_or_gate() {
+        let layout = "
+A###
+    #
+   #####
+    #
+B###
+";
+        for (a, b, expected) in [(false, false, vec![]), (true, false, vec![6]), (false, true, vec![6]), (true, true, vec![6])] {
+            assert_eq!(arrivals(&mut gate(layout, a, b), 2, 7, 30), expected, "{} or {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_xor_gate() {
+        let layout = "
+A####
+     #
+    ####
+    #  ######
+    ####
+     #
+B####
+";
+        for (a, b, expected) in [(false, false, vec![]), (true, false, vec![11]), (false, true, vec![11]), (true, true, vec![])] {
+            assert_eq!(arrivals(&mut gate(layout, a, b), 3, 12, 30), expected, "{} xor {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_clock() {
+        let layout = "
+ ~@##
+#    #
+#    #######
+#    #
+ ####
+";
+        let mut clock = circuit(layout);
+        let start = clock.cells_to_arr();
+        // the loop is 14 cells long, so an electron leaves it every 14 ticks
+        assert_eq!(arrivals(&mut clock, 2, 11, 60), vec![9, 23, 37, 51]);
+        let mut looped = circuit(layout);
+        for _ in 0..14 {
+            looped.tick();
+        }
+        assert_eq!(looped.cells_to_arr(), start);
+    }
+
+    #[test]
+    fn test_load_rle() {
+        // the wire of `test_wire` the way golly saves it
+        let pattern = rle::parse("x = 8, y = 1, rule = WireWorld\nBA6C!\n").unwrap();
+        assert_eq!(pattern.rule, Some(Rule::wireworld()));
+        let mut universe = Universe::new(10, 3);
+        universe.set_topology(Topology::Bounded);
+        universe.replace_rule(Rule::wireworld());
+        pattern.place(&mut universe, 1, 1).unwrap();
+        assert_eq!(universe.get_cell(1, 1), Ok(TAIL));
+        assert_eq!(universe.get_cell(1, 2), Ok(HEAD));
+        assert_eq!(arrivals(&mut universe, 1, 8, 10), vec![6]);
+        assert_eq!(universe.to_rle(), "x = 10, y = 3, rule = WireWorld\n$.8C!\n");
+    }
+}
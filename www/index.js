@@ -1,92 +1,91 @@
-import { Universe, Cell } from "game-of-life";
+import { Universe, Viewport } from "game-of-life";
 import { memory } from "game-of-life/game_of_life_bg.wasm";
 
-const CELL_SIZE = 5; // px
-const GRID_COLOR = "#CCCCCC";
-const DEAD_COLOR = "#FFFFFF";
-const ALIVE_COLOR = "#000000";
+const SCREEN_WIDTH = 640; // px
+const SCREEN_HEIGHT = 480; // px
 
-const universe = Universe.new(10, 10);
-universe.init_single_cell(2, 3);
-universe.init_single_cell(3, 4);
-universe.init_single_cell(4, 2);
-universe.init_single_cell(4, 3);
-universe.init_single_cell(4, 4);
+const universe = Universe.new(256, 256);
+universe.randomize(BigInt(Date.now()), 0.3);
 
 const height = universe.height();
 const width = universe.width();
 
-// Construct the universe, and get its width and height.
+// The viewport draws the visible part of the universe into a framebuffer
+// living in wasm memory, zoomed in or out to any power of two.
+const viewport = Viewport.new(SCREEN_WIDTH, SCREEN_HEIGHT);
+viewport.fit(universe);
 
-// Give the canvas room for all of our cells and a 1px border
-// around each of them.
 const canvas = document.getElementById("game-of-life-canvas");
-canvas.height = (CELL_SIZE + 1) * height + 1;
-canvas.width = (CELL_SIZE + 1) * width + 1;
+canvas.width = SCREEN_WIDTH;
+canvas.height = SCREEN_HEIGHT;
 
 const ctx = canvas.getContext('2d');
 
+const drawFrame = () => {
+  // Only the pixels showing cells that were born or died since the last frame
+  // are redrawn, unless the view was zoomed or panned in between.
+  viewport.render_changes(universe);
+  const pixels = new Uint8ClampedArray(memory.buffer, viewport.frame_ptr(), viewport.frame_len());
+  ctx.putImageData(new ImageData(pixels, SCREEN_WIDTH, SCREEN_HEIGHT), 0, 0);
+};
+
 const renderLoop = (timeout=0) => {
-    drawGrid();
-    drawCells();
-    
     universe.tick();
+    drawFrame();
+
     requestAnimationFrame(renderLoop);
 };
 
-const drawGrid = () => {
-    ctx.beginPath();
-    ctx.strokeStyle = GRID_COLOR;
-
-    // Vertical lines.
-    for (let i = 0; i <= width; i++) {
-        ctx.moveTo(i * (CELL_SIZE + 1) + 1, 0);
-        ctx.lineTo(i * (CELL_SIZE + 1) + 1, (CELL_SIZE + 1) * height + 1);
-    }
-
-    // Horizontal lines.
-    for (let j = 0; j <= height; j++) {
-        ctx.moveTo(0, j * (CELL_SIZE + 1) + 1);
-        ctx.lineTo((CELL_SIZE + 1) * width + 1, j * (CELL_SIZE + 1) + 1);
-    }
+const toCanvas = event => {
+  const boundingRect = canvas.getBoundingClientRect();
 
-    ctx.stroke();
-};
+  const scaleX = canvas.width / boundingRect.width;
+  const scaleY = canvas.height / boundingRect.height;
 
-const getIndex = (row, column) => {
-  return row * width + column;
+  return [
+    (event.clientX - boundingRect.left) * scaleX,
+    (event.clientY - boundingRect.top) * scaleY,
+  ];
 };
 
-const drawCells = () => {
-  const cellsPtr = universe.cells();
-  const cells = new Uint8Array(memory.buffer, cellsPtr, width * height);
-
-  ctx.beginPath();
+canvas.addEventListener("wheel", event => {
+  event.preventDefault();
+  const [x, y] = toCanvas(event);
+  viewport.zoom_at(x, y, event.deltaY < 0 ? 1 : -1);
+  drawFrame();
+});
 
-  for (let row = 0; row < height; row++) {
-    for (let col = 0; col < width; col++) {
-      const idx = getIndex(row, col);
+// Dragging pans the view, a click without moving toggles the cell under the pointer.
+let drag = null;
 
-      ctx.fillStyle = cells[idx] === Cell.Dead
-        ? DEAD_COLOR
-        : ALIVE_COLOR;
+canvas.addEventListener("mousedown", event => {
+  drag = { last: toCanvas(event), moved: false };
+});
 
-      ctx.fillRect(
-        col * (CELL_SIZE + 1) + 1,
-        row * (CELL_SIZE + 1) + 1,
-        CELL_SIZE,
-        CELL_SIZE
-      );
+canvas.addEventListener("mousemove", event => {
+  if (drag === null) {
+    return;
+  }
+  const [x, y] = toCanvas(event);
+  const [lastX, lastY] = drag.last;
+  if (Math.abs(x - lastX) + Math.abs(y - lastY) > 0) {
+    viewport.pan(x - lastX, y - lastY);
+    drag = { last: [x, y], moved: true };
+    drawFrame();
+  }
+});
+
+canvas.addEventListener("mouseup", event => {
+  if (drag !== null && !drag.moved) {
+    const [x, y] = toCanvas(event);
+    const [row, col] = viewport.screen_to_cell(x, y);
+    if (row >= 0 && row < height && col >= 0 && col < width) {
+      universe.toggle_cell(row, col);
+      drawFrame();
     }
   }
+  drag = null;
+});
 
-  ctx.stroke();
-};
-
+drawFrame();
 requestAnimationFrame(renderLoop);
-
-// const nextBtn = document.getElementById('next-btn');
-// nextBtn.onclick = () => {
-//     universe.tick();
-//     requestAnimationFrame(renderLoop);
-// }
\ No newline at end of file